@@ -1,155 +1,435 @@
 use std::{collections::BinaryHeap, fs::File};
 
-use itertools::Itertools;
-use trie_rs::{inc_search::Answer, map::Trie};
+use serde::{Deserialize, Serialize};
+use trie_rs::map::Trie;
 
-use crate::{ExpectedNext, NextState, NextStateExpected, Queries};
-
-fn log2(x: u64) -> u64 {
-    // u64::BITS as u64 - x.leading_zeros() as u64
-    (x as f64).log2() as u64
-}
+use crate::{
+    expand::{alphabet, PairMasks, Side},
+    has_diverged, is_canonical,
+    lookahead::Lookahead,
+    morphology::Morphology,
+    score::{score_words, Scoring, WordScorer},
+    solution::TextBuilder,
+    solver::{Outcome, SolverConfig, Stats},
+    CrackOptions, Dictionary, ExpectedNext, Queries, Solution,
+};
 
+#[allow(dead_code)]
 fn sqrt(x: u64) -> u64 {
     // u64::BITS as u64 - x.leading_zeros() as u64
     (x as f64).sqrt() as u64
 }
 
-pub fn build_trie_importance<'a>(words: impl Iterator<Item = (&'a str, u64)>) -> Trie<u8, u64> {
-    words
-        .map(|(word, x)| (word, log2(x) + 2 * (word.len() as u64)))
-        .collect()
+/// a trie of the words and counts scored with [`Scoring::default`]. the values are `f64`s, which
+/// may be fractional or negative
+pub fn build_trie_importance<'a>(words: impl Iterator<Item = (&'a str, u64)>) -> Trie<u8, f64> {
+    build_trie_scored(words, &Scoring::default())
+}
+
+/// like [`build_trie_importance`], with the forms `morphology` makes of the words
+pub fn build_trie_inflected<'a>(
+    words: impl Iterator<Item = (&'a str, u64)>,
+    morphology: &Morphology,
+) -> Trie<u8, f64> {
+    let words = morphology.apply(
+        words
+            .map(|(word, count)| (word.to_owned(), count))
+            .collect(),
+    );
+    build_trie_importance(words.iter().map(|(word, count)| (word.as_str(), *count)))
 }
 
-#[derive(Clone)]
-struct State<'a, 'b> {
-    queries_left: Queries<'a, u64>,
-    queries_right: Queries<'a, u64>,
-    importance: u64,
-    cipher: &'b [u8],
-    left: String,
-    right: String,
+pub fn build_trie_scored<'a>(
+    words: impl Iterator<Item = (&'a str, u64)>,
+    scorer: &impl WordScorer,
+) -> Trie<u8, f64> {
+    score_words(scorer, words).into_iter().collect()
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct State {
+    queries_left: Queries,
+    queries_right: Queries,
+    // the values of the words finished so far, which is the score of the solution once the
+    // cipher is read
+    importance: f64,
+    // the number of cipher bytes read so far
+    offset: usize,
+    left: TextBuilder,
+    right: TextBuilder,
     expected_next1: ExpectedNext,
     expected_next2: ExpectedNext,
+    diverged: bool,
 }
 
-impl<'a, 'b> PartialEq for State<'a, 'b> {
+impl PartialEq for State {
     fn eq(&self, other: &Self) -> bool {
-        self.left == other.left
-            && self.right == other.right
-            && self.cipher == other.cipher
+        self.left.text() == other.left.text()
+            && self.right.text() == other.right.text()
+            && self.offset == other.offset
             && self.expected_next1 == other.expected_next1
             && self.expected_next2 == other.expected_next2
     }
 }
 
-impl PartialOrd for State<'_, '_> {
+impl PartialOrd for State {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl Eq for State<'_, '_> {}
+impl Eq for State {}
 
-impl Ord for State<'_, '_> {
+impl Ord for State {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         self.importance
-            .cmp(&other.importance)
-            .then(self.cipher.len().cmp(&other.cipher.len()))
+            .total_cmp(&other.importance)
+            .then(other.offset.cmp(&self.offset))
     }
 }
 
-fn tasks_of_answer(ans: Answer) -> impl Iterator<Item = ExpectedNext> + Clone {
-    ans.is_match()
-        .then_some(ExpectedNext::Special)
-        .into_iter()
-        .chain(ans.is_prefix().then_some(ExpectedNext::Word))
-}
-
-pub fn crack_non_rec(cipher: &[u8], root: &Trie<u8, u64>) -> Vec<(String, String)> {
-    // make this a binary heap?
-    let mut heap = BinaryHeap::new();
-    heap.push(State {
-        importance: 0,
-        queries_left: Queries::new(root.inc_search()),
-        queries_right: Queries::new(root.inc_search()),
-        left: String::with_capacity(cipher.len()),
-        right: String::with_capacity(cipher.len()),
-        cipher,
-        expected_next1: ExpectedNext::Word,
-        expected_next2: ExpectedNext::Word,
-    });
+impl State {
+    fn start(cipher: &[u8], root: &impl Dictionary, options: CrackOptions) -> Self {
+        Self {
+            importance: 0.0,
+            queries_left: root.start(),
+            queries_right: root.start(),
+            left: TextBuilder::with_capacity(cipher.len()),
+            right: TextBuilder::with_capacity(cipher.len()),
+            offset: 0,
+            expected_next1: ExpectedNext::Word,
+            expected_next2: ExpectedNext::Word,
+            diverged: !options.break_symmetry,
+        }
+    }
 
-    let mut res = vec![];
-    let mut seen = 0usize;
+    /// the solutions of a state that read the whole cipher, which are the state itself and, if
+    /// only one of them was searched, its mirror image
+    fn finish(self, options: CrackOptions) -> Vec<Solution> {
+        if self.expected_next1 == ExpectedNext::Word || self.expected_next2 == ExpectedNext::Word {
+            return vec![];
+        }
+        let solution = Solution::new(self.left.build(), self.right.build());
+        let mirrored = (self.diverged && !options.unordered && options.break_symmetry)
+            .then(|| solution.mirrored());
+        std::iter::once(solution).chain(mirrored).collect()
+    }
 
+    fn expand(
+        &self,
+        cipher: &[u8],
+        root: &impl Dictionary<Value = f64>,
+        pair_masks: &PairMasks,
+        lookahead: Option<&Lookahead>,
+        out: &mut impl Extend<Self>,
+    ) {
+        let cipher = &cipher[self.offset..];
+        let side1 = Side::new(self.queries_left, self.expected_next1);
+        let side2 = Side::new(self.queries_right, self.expected_next2);
+
+        for (ch1, ch2) in pair_masks.pairs(cipher[0], side1.allowed(root), side2.allowed(root)) {
+            if !self.diverged && ch1 > ch2 {
+                continue;
+            }
+            for (next_side1, val1) in side1.next(root, ch1) {
+                for (next_side2, val2) in side2.next(root, ch2) {
+                    let (expected_next1, expected_next2) =
+                        (next_side1.expected, next_side2.expected);
+                    let (next1, next2) = ((ch1, expected_next1), (ch2, expected_next2));
+                    if !is_canonical(self.diverged, next1, next2) {
+                        continue;
+                    }
+                    if let Some(lookahead) = lookahead {
+                        if !lookahead.feasible(&next_side1, &next_side2, &cipher[1..], true) {
+                            continue;
+                        }
+                    }
+                    let mut left = self.left.clone();
+                    left.push(ch1, expected_next1, val1);
+                    let mut right = self.right.clone();
+                    right.push(ch2, expected_next2, val2);
+
+                    out.extend([State {
+                        queries_left: next_side1.queries,
+                        queries_right: next_side2.queries,
+                        offset: self.offset + 1,
+                        left,
+                        right,
+                        expected_next1,
+                        expected_next2,
+                        // only finished words count, like in the score of a solution
+                        importance: self.importance + val1.unwrap_or(0.0) + val2.unwrap_or(0.0),
+                        diverged: has_diverged(self.diverged, next1, next2),
+                    }]);
+                }
+            }
+        }
+    }
+}
+
+pub fn crack_non_rec(cipher: &[u8], root: &impl Dictionary<Value = f64>) -> Vec<Solution> {
+    crack_non_rec_with(cipher, root, CrackOptions::default())
+}
+
+pub fn crack_non_rec_with(
+    cipher: &[u8],
+    root: &impl Dictionary<Value = f64>,
+    options: CrackOptions,
+) -> Vec<Solution> {
     use std::io::Write;
     let mut f = File::create("res").unwrap();
 
-    while let Some(State {
-        queries_left,
-        queries_right,
-        cipher,
-        left,
-        right,
-        expected_next1,
-        expected_next2,
-        importance,
-    }) = heap.pop()
-    {
-        seen += 1;
-
-        if seen % 10_000 == 0 {
-            eprintln!("seen {seen} states, have {} 'valid' solutions", res.len());
-            f.flush().expect("failed to flush (ew)");
+    let config = SolverConfig {
+        options,
+        ..Default::default()
+    };
+    let outcome = best_first(cipher, root, &config, |importance, solution| {
+        let (left, right) = (&solution.left.text, &solution.right.text);
+        writeln!(&mut f, "{importance}\t{left}\t{right}").expect("failed to write");
+    });
+    f.flush().expect("failed to flush (ew)");
+
+    outcome.solutions
+}
+
+/// explores the states with the highest importance first, calling `found` for every solution
+pub(crate) fn best_first(
+    cipher: &[u8],
+    root: &impl Dictionary<Value = f64>,
+    config: &SolverConfig,
+    mut found: impl FnMut(f64, &Solution),
+) -> Outcome {
+    let mut search = BestFirstSearch::new(cipher, root, *config);
+    while search.advance(&mut found) {}
+    search.into_outcome()
+}
+
+/// everything a best-first search needs to continue later
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(crate) struct Frontier {
+    heap: BinaryHeap<State>,
+    pub solutions: Vec<Solution>,
+    pub stats: Stats,
+    done: bool,
+}
+
+impl Frontier {
+    pub fn start(cipher: &[u8], root: &impl Dictionary, options: CrackOptions) -> Self {
+        Self {
+            heap: BinaryHeap::from([State::start(cipher, root, options)]),
+            ..Default::default()
+        }
+    }
+
+    /// whether the states only point to nodes of `root`
+    pub fn belongs_to(&self, root: &impl Dictionary) -> bool {
+        root.are_nodes(
+            self.heap
+                .iter()
+                .flat_map(|s| s.queries_left.nodes().iter().chain(s.queries_right.nodes()))
+                .copied(),
+        )
+    }
+}
+
+pub(crate) struct BestFirstSearch<'a, D> {
+    cipher: &'a [u8],
+    root: &'a D,
+    config: SolverConfig,
+    pair_masks: PairMasks,
+    lookahead: Option<Lookahead>,
+    pub frontier: Frontier,
+}
+
+impl<'a, D: Dictionary<Value = f64>> BestFirstSearch<'a, D> {
+    pub fn new(cipher: &'a [u8], root: &'a D, config: SolverConfig) -> Self {
+        let frontier = Frontier::start(cipher, root, config.options);
+        Self::resume(cipher, root, config, frontier)
+    }
+
+    pub fn resume(cipher: &'a [u8], root: &'a D, config: SolverConfig, frontier: Frontier) -> Self {
+        let options = config.options;
+        Self {
+            cipher,
+            root,
+            config,
+            pair_masks: PairMasks::new(alphabet(root)),
+            lookahead: (options.lookahead > 0).then(|| Lookahead::new(root, options.lookahead)),
+            frontier,
         }
-        if cipher.is_empty()
-            && (expected_next1 == ExpectedNext::Word || expected_next2 == ExpectedNext::Word)
-        {
-            continue;
+    }
+
+    /// expands the most important state. returns `false` once the search is over
+    pub fn advance(&mut self, mut found: impl FnMut(f64, &Solution)) -> bool {
+        let options = self.config.options;
+        let Frontier {
+            heap,
+            solutions,
+            stats,
+            done,
+        } = &mut self.frontier;
+        if *done {
+            return false;
         }
-        if cipher.is_empty() {
-            writeln!(&mut f, "{importance}\t{left}\t{right}").expect("failed to write");
-            res.push((left, right));
-            continue;
+        if self.config.max_solutions == Some(0) {
+            *done = true;
+            return false;
         }
-        let it1 = match expected_next1 {
-            ExpectedNext::Word => NextStateExpected::Word(Box::new(NextState::new(queries_left))),
-            ExpectedNext::Special => NextStateExpected::Special { i: 0, root },
-        };
-        let it2 = match expected_next2 {
-            ExpectedNext::Word => NextStateExpected::Word(Box::new(NextState::new(queries_right))),
-            ExpectedNext::Special => NextStateExpected::Special { i: 0, root },
+        let Some(state) = heap.pop() else {
+            *done = true;
+            stats.exhaustive = stats.dropped == 0;
+            return false;
         };
+        stats.expanded += 1;
 
-        for ((ch1, ans1, val1, queries_left), (ch2, ans2, val2, queries_right)) in it1
-            .cartesian_product(it2)
-            .filter(|&((left, _, _, _), (right, _, _, _))| left ^ cipher[0] == right)
-        {
-            let mut left = left.clone();
-            left.push(ch1 as char);
-            let mut right = right.clone();
-            right.push(ch2 as char);
-
-            let importance = importance + val1.unwrap_or(&0) + val2.unwrap_or(&0);
-
-            let tasks = tasks_of_answer(ans1).cartesian_product(tasks_of_answer(ans2));
-
-            for (expected_next1, expected_next2) in tasks {
-                heap.push(State {
-                    // yes, this clones one time too much, but do I care?
-                    queries_left: queries_left.clone(),
-                    queries_right: queries_right.clone(),
-                    cipher: &cipher[1..],
-                    left: left.clone(),
-                    right: right.clone(),
-                    expected_next1,
-                    expected_next2,
-                    importance,
-                });
+        if stats.expanded % 10_000 == 0 {
+            eprintln!(
+                "seen {} states, have {} 'valid' solutions",
+                stats.expanded,
+                solutions.len()
+            );
+        }
+        if state.offset == self.cipher.len() {
+            let max = self.config.max_solutions.unwrap_or(usize::MAX);
+            let importance = state.importance;
+            let finished = state.finish(options);
+            // with `max` solutions, the search goes on to tell whether there is another one, like
+            // the depth-first search does
+            if solutions.len() >= max {
+                *done = !finished.is_empty();
+                return !*done;
+            }
+            for solution in finished {
+                found(importance, &solution);
+                solutions.push(solution);
+            }
+            if solutions.len() > max {
+                solutions.truncate(max);
+                *done = true;
+                return false;
             }
+            return true;
         }
+        state.expand(
+            self.cipher,
+            self.root,
+            &self.pair_masks,
+            self.lookahead.as_ref(),
+            heap,
+        );
+        if let Some(max) = self.config.max_states.filter(|&max| heap.len() > max) {
+            stats.dropped += prune(heap, max) as u64;
+        }
+        true
     }
 
-    res
+    pub fn into_outcome(self) -> Outcome {
+        Outcome {
+            solutions: self.frontier.solutions,
+            stats: self.frontier.stats,
+        }
+    }
+}
+
+// drops the least important states of a heap that grew past `max`. it keeps some room below
+// `max`, so the heap isn't rebuilt on every push
+fn prune(heap: &mut BinaryHeap<State>, max: usize) -> usize {
+    let keep = (max - max / 4).max(1);
+    let mut states = std::mem::take(heap).into_vec();
+    let dropped = states.len().saturating_sub(keep);
+    if dropped > 0 {
+        states.select_nth_unstable_by(keep - 1, |a, b| b.cmp(a));
+        states.truncate(keep);
+    }
+    *heap = states.into();
+    dropped
+}
+
+/// advances all states one byte at a time, keeping only the `width` most important states
+pub(crate) fn beam(
+    cipher: &[u8],
+    root: &impl Dictionary<Value = f64>,
+    config: &SolverConfig,
+    width: usize,
+) -> Outcome {
+    let options = config.options;
+    let pair_masks = PairMasks::new(alphabet(root));
+    let lookahead = (options.lookahead > 0).then(|| Lookahead::new(root, options.lookahead));
+    let mut stats = Stats {
+        exhaustive: true,
+        ..Default::default()
+    };
+
+    let mut beam = vec![State::start(cipher, root, options)];
+    for _ in 0..cipher.len() {
+        let mut next = vec![];
+        for state in &beam {
+            stats.expanded += 1;
+            state.expand(cipher, root, &pair_masks, lookahead.as_ref(), &mut next);
+        }
+        if next.len() > width {
+            next.sort_by(|a, b| b.cmp(a));
+            stats.dropped += (next.len() - width) as u64;
+            next.truncate(width);
+            stats.exhaustive = false;
+        }
+        beam = next;
+    }
+
+    beam.sort_by(|a, b| b.cmp(a));
+    let mut solutions = beam
+        .into_iter()
+        .flat_map(|state| state.finish(options))
+        .collect::<Vec<_>>();
+    if let Some(max) = config.max_solutions {
+        if solutions.len() > max {
+            solutions.truncate(max);
+            stats.exhaustive = false;
+        }
+    }
+
+    Outcome { solutions, stats }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture, xor_strings};
+
+    #[test]
+    fn caps_the_number_of_states() {
+        let root = fixture::dict();
+        let cipher = xor_strings("yes the few", "the yes kha");
+        let config = SolverConfig {
+            max_states: Some(8),
+            ..Default::default()
+        };
+
+        let mut search = BestFirstSearch::new(&cipher, &root, config);
+        loop {
+            let before = search.frontier.heap.len();
+            let dropped = search.frontier.stats.dropped;
+            let mut children = vec![];
+            if let Some(top) = search.frontier.heap.peek() {
+                if top.offset < cipher.len() {
+                    top.expand(&cipher, &root, &search.pair_masks, None, &mut children);
+                }
+            }
+            if !search.advance(|_, _| {}) {
+                break;
+            }
+            let after = search.frontier.heap.len();
+            assert!(after <= 8);
+            // every state that isn't kept was counted as dropped
+            let dropped = (search.frontier.stats.dropped - dropped) as usize;
+            assert_eq!(after + dropped, before - 1 + children.len());
+        }
+
+        let outcome = search.into_outcome();
+        assert!(outcome.stats.dropped > 0);
+        assert!(!outcome.stats.exhaustive);
+        let all = best_first(&cipher, &root, &Default::default(), |_, _| {});
+        assert!(all.stats.exhaustive);
+        assert!(outcome.solutions.iter().all(|s| all.solutions.contains(s)));
+    }
 }
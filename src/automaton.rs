@@ -0,0 +1,341 @@
+use std::{collections::BTreeMap, hash::Hasher};
+
+use serde::{Deserialize, Serialize};
+use trie_rs::{inc_search::Answer, map::Trie};
+
+use crate::{byteset::ByteSet, dictionary::Fnv, Dictionary, WordValue};
+
+/// a position in a [`Dictionary`]
+pub type NodeId = u64;
+
+/// a trie compiled into flat arrays. nodes are numbered in breadth first order with the root
+/// being `0`, the children of a node are stored contiguously and sorted by their label, so the
+/// child for a byte is found by ranking the byte in the node's child mask
+#[derive(Debug, Clone)]
+pub struct Automaton<T> {
+    first_child: Vec<u32>,
+    masks: Vec<ByteSet>,
+    values: Vec<Option<T>>,
+}
+
+impl<T> Automaton<T> {
+    pub const ROOT: NodeId = 0;
+
+    pub fn node_count(&self) -> usize {
+        self.masks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.masks.len() == 1 && self.values[0].is_none()
+    }
+
+    /// the parent of `node` and the label of the edge between them
+    pub fn parent(&self, node: NodeId) -> Option<(NodeId, u8)> {
+        if node == Self::ROOT {
+            return None;
+        }
+        // `first_child` is sorted, the last node whose children start at or before `node` is the
+        // parent
+        let parent = self
+            .first_child
+            .partition_point(|&first| first as NodeId <= node)
+            - 1;
+        let label = self.masks[parent]
+            .select((node - self.first_child[parent] as NodeId) as usize)
+            .expect("node is a child of its parent");
+        Some((parent as NodeId, label))
+    }
+
+    /// the bytes on the path from the root to `node`
+    pub fn word(&self, mut node: NodeId) -> Vec<u8> {
+        let mut word = vec![];
+        while let Some((parent, label)) = self.parent(node) {
+            word.push(label);
+            node = parent;
+        }
+        word.reverse();
+        word
+    }
+}
+
+impl<T: Copy + WordValue> Dictionary for Automaton<T> {
+    type Value = T;
+
+    fn root(&self) -> NodeId {
+        Self::ROOT
+    }
+
+    fn children(&self, node: NodeId) -> ByteSet {
+        self.masks[node as usize]
+    }
+
+    fn child(&self, node: NodeId, label: u8) -> Option<NodeId> {
+        let mask = &self.masks[node as usize];
+        mask.contains(label)
+            .then(|| (self.first_child[node as usize] + mask.rank(label) as u32) as NodeId)
+    }
+
+    fn value(&self, node: NodeId) -> Option<T> {
+        self.values[node as usize]
+    }
+
+    fn is_word(&self, node: NodeId) -> bool {
+        self.values[node as usize].is_some()
+    }
+
+    fn is_node(&self, node: NodeId) -> bool {
+        node < self.node_count() as NodeId
+    }
+
+    fn fingerprint(&self) -> u64 {
+        let mut hash = Fnv::default();
+        for ((&first, mask), value) in self.first_child.iter().zip(&self.masks).zip(&self.values) {
+            hash.write_u32(first);
+            mask.words().iter().for_each(|&w| hash.write_u64(w));
+            hash.write_u64(value.map_or(u64::MAX, |v| v.score().to_bits()));
+        }
+        hash.finish()
+    }
+}
+
+impl<T> Automaton<T> {
+    /// the arrays the automaton is made of, see [`Automaton::from_parts`]
+    pub(crate) fn parts(&self) -> (&[u32], &[ByteSet], &[Option<T>]) {
+        (&self.first_child, &self.masks, &self.values)
+    }
+
+    /// `first_child[i]` has to be the number of the first child of node `i`, the children of every
+    /// node following those of the node before it
+    pub(crate) fn from_parts(
+        first_child: Vec<u32>,
+        masks: Vec<ByteSet>,
+        values: Vec<Option<T>>,
+    ) -> Self {
+        debug_assert!(first_child.len() == masks.len() && masks.len() == values.len());
+        Self {
+            first_child,
+            masks,
+            values,
+        }
+    }
+}
+
+#[derive(Default)]
+struct BuildNode<T> {
+    children: BTreeMap<u8, usize>,
+    value: Option<T>,
+}
+
+impl<K, T> FromIterator<(K, T)> for Automaton<T>
+where
+    K: AsRef<[u8]>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, T)>>(iter: I) -> Self {
+        let mut nodes = vec![BuildNode {
+            children: BTreeMap::new(),
+            value: None,
+        }];
+
+        for (word, value) in iter {
+            let mut node = 0;
+            for &b in word.as_ref() {
+                node = match nodes[node].children.get(&b) {
+                    Some(&child) => child,
+                    None => {
+                        nodes.push(BuildNode {
+                            children: BTreeMap::new(),
+                            value: None,
+                        });
+                        let child = nodes.len() - 1;
+                        nodes[node].children.insert(b, child);
+                        child
+                    }
+                };
+            }
+            nodes[node].value = Some(value);
+        }
+
+        // renumber in bfs order so that siblings are adjacent
+        let mut order = Vec::with_capacity(nodes.len());
+        order.push(0);
+        let mut i = 0;
+        while i < order.len() {
+            order.extend(nodes[order[i]].children.values().copied());
+            i += 1;
+        }
+
+        let mut first_child = Vec::with_capacity(nodes.len());
+        let mut masks = Vec::with_capacity(nodes.len());
+        let mut next_child = 1;
+        for &old in &order {
+            let children = &nodes[old].children;
+            first_child.push(next_child as u32);
+            masks.push(children.keys().copied().collect());
+            next_child += children.len();
+        }
+
+        let mut nodes = nodes;
+        let values = order.iter().map(|&old| nodes[old].value.take()).collect();
+
+        Self {
+            first_child,
+            masks,
+            values,
+        }
+    }
+}
+
+impl<T: Clone> From<&Trie<u8, T>> for Automaton<T> {
+    fn from(trie: &Trie<u8, T>) -> Self {
+        trie.iter::<Vec<u8>, _>()
+            .map(|(word, value)| (word, value.clone()))
+            .collect()
+    }
+}
+
+/// [`Queries`] hold at most [`Queries::CAPACITY`] nodes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TooManyQueries;
+
+impl std::fmt::Display for TooManyQueries {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "more than {} simultaneous queries", Queries::CAPACITY)
+    }
+}
+
+impl std::error::Error for TooManyQueries {}
+
+/// stored as the list of its nodes, so that a saved search can't hold more than fit
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "Vec<NodeId>", into = "Vec<NodeId>")]
+pub struct Queries {
+    len: u8,
+    // unused slots are always zero so that the derived traits only look at the set
+    nodes: [NodeId; Self::CAPACITY],
+}
+
+impl Queries {
+    pub const CAPACITY: usize = 4;
+
+    pub fn new(node: NodeId) -> Self {
+        let mut nodes = [0; Self::CAPACITY];
+        nodes[0] = node;
+        Self { len: 1, nodes }
+    }
+
+    pub fn from_nodes(nodes: impl IntoIterator<Item = NodeId>) -> Result<Self, TooManyQueries> {
+        let mut res = Self {
+            len: 0,
+            nodes: [0; Self::CAPACITY],
+        };
+        for node in nodes {
+            res.insert(node)?;
+        }
+        Ok(res)
+    }
+
+    /// adds `node` unless it is there already
+    pub fn insert(&mut self, node: NodeId) -> Result<(), TooManyQueries> {
+        let Err(pos) = self.nodes().binary_search(&node) else {
+            return Ok(());
+        };
+        if self.len as usize == Self::CAPACITY {
+            return Err(TooManyQueries);
+        }
+        self.nodes.copy_within(pos..self.len as usize, pos + 1);
+        self.nodes[pos] = node;
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn nodes(&self) -> &[NodeId] {
+        &self.nodes[..self.len as usize]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn children(&self, dict: &impl Dictionary) -> ByteSet {
+        self.nodes()
+            .iter()
+            .fold(ByteSet::EMPTY, |acc, &n| acc | dict.children(n))
+    }
+
+    pub fn advance_all(&mut self, dict: &impl Dictionary, q: u8) {
+        *self = Self::from_nodes(self.nodes().iter().filter_map(|&n| dict.child(n, q)))
+            .expect("every node has at most one child per byte");
+    }
+
+    pub fn answer(&self, dict: &impl Dictionary) -> Option<Answer> {
+        let is_prefix = self.nodes().iter().any(|&n| dict.has_children(n));
+        let is_match = self.nodes().iter().any(|&n| dict.is_word(n));
+        match (is_prefix, is_match) {
+            (true, false) => Some(Answer::Prefix),
+            (false, true) => Some(Answer::Match),
+            (true, true) => Some(Answer::PrefixAndMatch),
+            (false, false) => None,
+        }
+    }
+
+    pub fn value<D: Dictionary>(&self, dict: &D) -> Option<D::Value> {
+        self.nodes().iter().find_map(|&n| dict.value(n))
+    }
+}
+
+impl TryFrom<Vec<NodeId>> for Queries {
+    type Error = TooManyQueries;
+
+    fn try_from(nodes: Vec<NodeId>) -> Result<Self, Self::Error> {
+        Self::from_nodes(nodes)
+    }
+}
+
+impl From<Queries> for Vec<NodeId> {
+    fn from(queries: Queries) -> Self {
+        queries.nodes().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compiles_trie() {
+        let automaton: Automaton<u64> = [("yes", 1), ("year", 2), ("you", 3), ("y", 4)]
+            .into_iter()
+            .collect();
+
+        assert_eq!(automaton.get("yes"), Some(1));
+        assert_eq!(automaton.get("year"), Some(2));
+        assert_eq!(automaton.get("y"), Some(4));
+        assert_eq!(automaton.get("ye"), None);
+        assert_eq!(automaton.walk("yeah"), None);
+        assert_eq!(automaton.word(automaton.walk("year").unwrap()), b"year");
+        assert_eq!(automaton.word(automaton.root()), b"");
+        assert!(automaton.is_node(automaton.walk("year").unwrap()));
+        assert!(!automaton.is_node(automaton.node_count() as NodeId));
+
+        let ye = automaton.walk("ye").unwrap();
+        assert_eq!(automaton.children(ye).iter().collect::<Vec<_>>(), b"as");
+        assert_eq!(automaton.answer(ye), Some(Answer::Prefix));
+        assert_eq!(
+            automaton.answer(automaton.walk("y").unwrap()),
+            Some(Answer::PrefixAndMatch)
+        );
+    }
+
+    #[test]
+    fn queries_stay_sorted() {
+        let q = Queries::from_nodes([5, 1, 3, 1]).unwrap();
+        assert_eq!(q.nodes(), [1, 3, 5]);
+        assert_eq!(q, Queries::from_nodes([3, 5, 1]).unwrap());
+        assert_eq!(Queries::from_nodes(0..5), Err(TooManyQueries));
+
+        let json = serde_json::to_string(&q).unwrap();
+        assert_eq!(json, "[1,3,5]");
+        assert_eq!(serde_json::from_str::<Queries>(&json).unwrap(), q);
+        assert!(serde_json::from_str::<Queries>("[1,2,3,4,5]").is_err());
+    }
+}
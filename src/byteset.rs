@@ -0,0 +1,151 @@
+use std::ops::{BitAnd, BitOr, Not};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ByteSet([u64; 4]);
+
+impl ByteSet {
+    pub const EMPTY: Self = Self([0; 4]);
+    pub const FULL: Self = Self([u64::MAX; 4]);
+
+    pub const fn from_words(words: [u64; 4]) -> Self {
+        Self(words)
+    }
+
+    pub const fn words(&self) -> [u64; 4] {
+        self.0
+    }
+
+    pub const fn from_bytes(bytes: &[u8]) -> Self {
+        let mut set = Self::EMPTY;
+        let mut i = 0;
+        while i < bytes.len() {
+            set.0[bytes[i] as usize / 64] |= 1 << (bytes[i] % 64);
+            i += 1;
+        }
+        set
+    }
+
+    pub fn insert(&mut self, b: u8) {
+        self.0[b as usize / 64] |= 1 << (b % 64);
+    }
+
+    pub fn remove(&mut self, b: u8) {
+        self.0[b as usize / 64] &= !(1 << (b % 64));
+    }
+
+    pub const fn contains(&self, b: u8) -> bool {
+        self.0[b as usize / 64] & (1 << (b % 64)) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == [0; 4]
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|x| x.count_ones() as usize).sum()
+    }
+
+    /// number of elements smaller than `b`
+    pub fn rank(&self, b: u8) -> usize {
+        let word = b as usize / 64;
+        let below = self.0[word] & ((1 << (b % 64)) - 1);
+        self.0[..word]
+            .iter()
+            .map(|x| x.count_ones() as usize)
+            .sum::<usize>()
+            + below.count_ones() as usize
+    }
+
+    /// the `k`-th smallest element
+    pub fn select(&self, k: usize) -> Option<u8> {
+        self.iter().nth(k)
+    }
+
+    /// the set `{ b ^ x | b in self }`
+    pub fn xor(&self, x: u8) -> Self {
+        const LOW_HALVES: [u64; 6] = [
+            0x5555_5555_5555_5555,
+            0x3333_3333_3333_3333,
+            0x0f0f_0f0f_0f0f_0f0f,
+            0x00ff_00ff_00ff_00ff,
+            0x0000_ffff_0000_ffff,
+            0x0000_0000_ffff_ffff,
+        ];
+
+        // xor-ing with the low 6 bits swaps blocks inside of a word, the high 2 bits swap words
+        let words = self.0.map(|mut w| {
+            for (j, mask) in LOW_HALVES.iter().enumerate() {
+                if x & (1 << j) != 0 {
+                    let shift = 1 << j;
+                    w = ((w & mask) << shift) | ((w >> shift) & mask);
+                }
+            }
+            w
+        });
+        Self(std::array::from_fn(|i| words[i ^ (x as usize >> 6)]))
+    }
+
+    pub fn iter(&self) -> Iter {
+        Iter {
+            words: self.0,
+            word: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Iter {
+    words: [u64; 4],
+    word: usize,
+}
+
+impl Iterator for Iter {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        while self.word < 4 {
+            let w = &mut self.words[self.word];
+            if *w != 0 {
+                let bit = w.trailing_zeros();
+                *w &= *w - 1;
+                return Some((self.word * 64) as u8 + bit as u8);
+            }
+            self.word += 1;
+        }
+        None
+    }
+}
+
+impl FromIterator<u8> for ByteSet {
+    fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Self {
+        let mut set = Self::EMPTY;
+        for b in iter {
+            set.insert(b);
+        }
+        set
+    }
+}
+
+impl BitAnd for ByteSet {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] & rhs.0[i]))
+    }
+}
+
+impl BitOr for ByteSet {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] | rhs.0[i]))
+    }
+}
+
+impl Not for ByteSet {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(self.0.map(|x| !x))
+    }
+}
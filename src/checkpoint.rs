@@ -0,0 +1,328 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    all::{self, BestFirstSearch},
+    dfs::{self, Search},
+    invalid, Dictionary, Outcome, Solution, SolverConfig, Stats, Strategy,
+};
+
+const VERSION: u32 = 4;
+
+// how many states are expanded between looking at the clock and the stop flag
+const CHECK_EVERY: u64 = 1024;
+
+#[derive(Clone, Serialize, Deserialize)]
+enum Frontier {
+    BestFirst(all::Frontier),
+    DepthFirst {
+        search: dfs::Frontier,
+        solutions: Vec<Solution>,
+        stats: Stats,
+    },
+}
+
+/// a best-first or depth-first search that can be saved to a file and continued later with
+/// [`Checkpoint::run`]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    version: u32,
+    pub cipher: Vec<u8>,
+    pub config: SolverConfig,
+    // the fingerprint of the dictionary the search started with, a hash of its words, as the
+    // states point into it
+    dictionary: u64,
+    frontier: Frontier,
+}
+
+/// when [`Checkpoint::run`] saves its progress
+#[derive(Debug, Clone)]
+pub struct Checkpointing {
+    pub path: PathBuf,
+    /// save at least this often
+    pub interval: Option<Duration>,
+    /// save and stop once this is set, e.g. by a ctrl-c handler
+    pub stop: Arc<AtomicBool>,
+}
+
+pub enum Run {
+    Finished(Outcome),
+    /// the search was stopped and saved
+    Stopped(Box<Checkpoint>),
+}
+
+enum Due {
+    Nothing,
+    Save,
+    Stop,
+}
+
+struct Clock {
+    steps: u64,
+    last: Instant,
+}
+
+impl Clock {
+    fn new() -> Self {
+        Self {
+            steps: 0,
+            last: Instant::now(),
+        }
+    }
+
+    fn tick(&mut self, checkpointing: &Checkpointing) -> Due {
+        self.steps += 1;
+        if self.steps % CHECK_EVERY != 0 {
+            return Due::Nothing;
+        }
+        if checkpointing.stop.load(Ordering::Relaxed) {
+            return Due::Stop;
+        }
+        match checkpointing.interval {
+            Some(interval) if self.last.elapsed() >= interval => {
+                self.last = Instant::now();
+                Due::Save
+            }
+            _ => Due::Nothing,
+        }
+    }
+}
+
+impl Checkpoint {
+    /// the start of a search with `strategy`, which has to be depth-first or best-first
+    pub fn new(
+        cipher: &[u8],
+        root: &impl Dictionary<Value = f64>,
+        strategy: Strategy,
+        config: SolverConfig,
+    ) -> Result<Self, String> {
+        let frontier = match strategy {
+            Strategy::BestFirst => {
+                Frontier::BestFirst(all::Frontier::start(cipher, root, config.options))
+            }
+            Strategy::DepthFirst => {
+                let q = root.start();
+                Frontier::DepthFirst {
+                    search: Search::new(cipher, root, q, q, config.options, true).frontier(),
+                    solutions: vec![],
+                    stats: Stats::default(),
+                }
+            }
+            _ => return Err(format!("{strategy:?} search can't be checkpointed")),
+        };
+        Ok(Self {
+            version: VERSION,
+            cipher: cipher.to_vec(),
+            config,
+            dictionary: root.fingerprint(),
+            frontier,
+        })
+    }
+
+    /// the solutions found so far
+    pub fn solutions(&self) -> &[Solution] {
+        match &self.frontier {
+            Frontier::BestFirst(frontier) => &frontier.solutions,
+            Frontier::DepthFirst { solutions, .. } => solutions,
+        }
+    }
+
+    pub fn stats(&self) -> Stats {
+        match &self.frontier {
+            Frontier::BestFirst(frontier) => frontier.stats,
+            Frontier::DepthFirst { stats, .. } => *stats,
+        }
+    }
+
+    /// writes the checkpoint next to `path` first, so a crash while saving keeps the old one
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_vec(self).map_err(io::Error::other)?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, path)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let checkpoint: Self = serde_json::from_slice(&fs::read(path)?).map_err(invalid)?;
+        if checkpoint.version != VERSION {
+            return Err(invalid(format!(
+                "checkpoint version {} is not supported, expected {VERSION}",
+                checkpoint.version
+            )));
+        }
+        Ok(checkpoint)
+    }
+
+    fn with_frontier(&self, frontier: Frontier) -> Self {
+        Self {
+            version: VERSION,
+            cipher: self.cipher.clone(),
+            config: self.config,
+            dictionary: self.dictionary,
+            frontier,
+        }
+    }
+
+    /// continues the search until it is over or `checkpointing.stop` is set. `root` has to be the
+    /// dictionary the search started with
+    pub fn run(
+        self,
+        root: &impl Dictionary<Value = f64>,
+        checkpointing: &Checkpointing,
+    ) -> io::Result<Run> {
+        if root.fingerprint() != self.dictionary {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the checkpoint was made with another dictionary",
+            ));
+        }
+        let known = match &self.frontier {
+            Frontier::BestFirst(frontier) => frontier.belongs_to(root),
+            Frontier::DepthFirst { search, .. } => search.belongs_to(root),
+        };
+        if !known {
+            return Err(invalid(
+                "the checkpoint has nodes the dictionary doesn't know",
+            ));
+        }
+
+        let mut clock = Clock::new();
+        match self.frontier.clone() {
+            Frontier::BestFirst(frontier) => {
+                let mut search = BestFirstSearch::resume(&self.cipher, root, self.config, frontier);
+                while search.advance(|_, _| {}) {
+                    let due = clock.tick(checkpointing);
+                    if matches!(due, Due::Nothing) {
+                        continue;
+                    }
+                    let checkpoint =
+                        self.with_frontier(Frontier::BestFirst(search.frontier.clone()));
+                    checkpoint.save(&checkpointing.path)?;
+                    if matches!(due, Due::Stop) {
+                        return Ok(Run::Stopped(Box::new(checkpoint)));
+                    }
+                }
+                Ok(Run::Finished(search.into_outcome()))
+            }
+            Frontier::DepthFirst {
+                search,
+                mut solutions,
+                mut stats,
+            } => {
+                let limit = self.config.max_solutions.unwrap_or(usize::MAX);
+                let mut search = Search::resume(&self.cipher, root, self.config.options, search);
+                while solutions.len() < limit {
+                    let Some(found) = search.advance() else {
+                        break;
+                    };
+                    solutions.extend(found);
+                    stats.expanded = search.expanded();
+
+                    let due = clock.tick(checkpointing);
+                    if matches!(due, Due::Nothing) {
+                        continue;
+                    }
+                    let checkpoint = self.with_frontier(Frontier::DepthFirst {
+                        search: search.frontier(),
+                        solutions: solutions.clone(),
+                        stats,
+                    });
+                    checkpoint.save(&checkpointing.path)?;
+                    if matches!(due, Due::Stop) {
+                        return Ok(Run::Stopped(Box::new(checkpoint)));
+                    }
+                }
+                stats.exhaustive = solutions.len() < limit || search.next_solution().is_none();
+                Ok(Run::Finished(Outcome { solutions, stats }))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture, Automaton, Solver};
+
+    #[test]
+    fn resumes_where_it_stopped() {
+        let root = fixture::dict();
+        let cipher = fixture::cipher();
+        let config = SolverConfig::default();
+        let path = std::env::temp_dir().join(format!("xoracle-{}.checkpoint", std::process::id()));
+
+        for strategy in [Strategy::BestFirst, Strategy::DepthFirst] {
+            let expected = strategy.solve(&cipher, &root, &config);
+
+            // stop a search after a few states and go through the file
+            let start = Checkpoint::new(&cipher, &root, strategy, config).unwrap();
+            let frontier = match start.frontier.clone() {
+                Frontier::BestFirst(frontier) => {
+                    let mut search = BestFirstSearch::resume(&cipher, &root, config, frontier);
+                    for _ in 0..3 {
+                        search.advance(|_, _| {});
+                    }
+                    Frontier::BestFirst(search.frontier)
+                }
+                Frontier::DepthFirst { search, .. } => {
+                    let mut search = Search::resume(&cipher, &root, config.options, search);
+                    let solutions = (0..3).filter_map(|_| search.advance()?).collect();
+                    Frontier::DepthFirst {
+                        search: search.frontier(),
+                        solutions,
+                        stats: Stats::default(),
+                    }
+                }
+            };
+            let checkpointing = Checkpointing {
+                path: path.clone(),
+                interval: None,
+                stop: Arc::default(),
+            };
+            // the states point to nodes a smaller dictionary doesn't have
+            let tiny: Automaton<f64> = [("a", 1.0)].into_iter().collect();
+            let foreign = Checkpoint::new(&cipher, &tiny, strategy, config)
+                .unwrap()
+                .with_frontier(frontier.clone());
+            let err = foreign.run(&tiny, &checkpointing).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{strategy:?}");
+
+            start.with_frontier(frontier).save(&path).unwrap();
+            let Run::Finished(outcome) = Checkpoint::load(&path)
+                .unwrap()
+                .run(&root, &checkpointing)
+                .unwrap()
+            else {
+                panic!("the search wasn't stopped");
+            };
+            assert_eq!(outcome.solutions, expected.solutions, "{strategy:?}");
+            assert!(outcome.stats.exhaustive);
+        }
+
+        fs::remove_file(&path).unwrap();
+        assert!(Checkpoint::new(&cipher, &root, Strategy::Viterbi, config).is_err());
+
+        // the same number of nodes, but other words
+        let other: Automaton<f64> = [("the", 3.0), ("yes", 2.0), ("kha", 1.0), ("fez", 1.0)]
+            .into_iter()
+            .collect();
+        assert_eq!(other.node_count(), root.node_count());
+        let checkpointing = Checkpointing {
+            path,
+            interval: None,
+            stop: Arc::default(),
+        };
+        let start = Checkpoint::new(&cipher, &root, Strategy::BestFirst, config).unwrap();
+        assert!(start.run(&other, &checkpointing).is_err());
+    }
+}
@@ -0,0 +1,237 @@
+use std::{fs, hash::Hasher, io, path::Path};
+
+use crate::{byteset::ByteSet, charset, dictionary::Fnv, invalid, Automaton};
+
+const MAGIC: &[u8; 8] = b"xoradict";
+const VERSION: u32 = 2;
+const HEADER_LEN: usize = 32;
+// marks nodes that don't end a word in the array of values, a nan no score can be
+const NO_VALUE: u64 = u64::MAX;
+
+/// a scored dictionary in a binary file that loads without rebuilding the trie. loading copies
+/// the sections into the arrays of an [`Automaton`], see [`crate::transducer::FstDict`] for a
+/// dictionary that is used straight from a memory mapped file.
+///
+/// all numbers are little endian and every section starts at a multiple of 8 bytes:
+///
+/// - header: `b"xoradict"`, version (`u32`), number of nodes `n` (`u32`), length of the alphabet
+///   (`u32`), `0u32`, fnv-1a checksum of everything after the header (`u64`)
+/// - the alphabet, padded with zeros
+/// - `n` child masks of 4 `u64`s each
+/// - `n` values (the bits of `f64`s), `u64::MAX` for nodes that aren't words
+/// - `n` first children (`u32`), padded with zeros
+#[derive(Debug, Clone)]
+pub struct CompiledDict {
+    /// the bytes the words were allowed to use, [`crate::charset`] when the file was written.
+    /// files for another charset are rejected, as the searches couldn't spell their words
+    pub alphabet: Vec<u8>,
+    pub automaton: Automaton<f64>,
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = Fnv::default();
+    hash.write(bytes);
+    hash.finish()
+}
+
+fn padded(len: usize) -> usize {
+    len.next_multiple_of(8)
+}
+
+// reads the little endian numbers of a section
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(invalid("the dictionary is truncated"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+impl CompiledDict {
+    /// the words spelled with bytes of [`crate::charset`], others are skipped
+    pub fn new<K: AsRef<[u8]>>(words: impl IntoIterator<Item = (K, f64)>) -> Self {
+        let alphabet = charset();
+        let allowed = ByteSet::from_bytes(alphabet);
+        let automaton = words
+            .into_iter()
+            .filter(|(word, _)| word.as_ref().iter().all(|&b| allowed.contains(b)))
+            .collect();
+        Self {
+            alphabet: alphabet.to_vec(),
+            automaton,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (first_child, masks, values) = self.automaton.parts();
+        let n = masks.len();
+
+        let mut body = Vec::with_capacity(padded(self.alphabet.len()) + n * 44 + 8);
+        body.extend_from_slice(&self.alphabet);
+        body.resize(padded(body.len()), 0);
+        for mask in masks {
+            for word in mask.words() {
+                body.extend_from_slice(&word.to_le_bytes());
+            }
+        }
+        for value in values {
+            body.extend_from_slice(&value.map_or(NO_VALUE, f64::to_bits).to_le_bytes());
+        }
+        for first in first_child {
+            body.extend_from_slice(&first.to_le_bytes());
+        }
+        body.resize(padded(body.len()), 0);
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + body.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(n as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.alphabet.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&fnv1a(&body).to_le_bytes());
+        bytes.extend_from_slice(&body);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let mut header = Reader { bytes };
+        if header.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a compiled dictionary"));
+        }
+        let version = header.u32()?;
+        if version != VERSION {
+            return Err(invalid(format!(
+                "dictionary version {version} is not supported, expected {VERSION}"
+            )));
+        }
+        let n = header.u32()? as usize;
+        let alphabet_len = header.u32()? as usize;
+        header.u32()?;
+        let checksum = header.u64()?;
+
+        let body = header.bytes;
+        let expected_len = padded(alphabet_len) + n * 40 + padded(n * 4);
+        if body.len() != expected_len {
+            return Err(invalid("the dictionary has the wrong length"));
+        }
+        if fnv1a(body) != checksum {
+            return Err(invalid("the dictionary's checksum doesn't match"));
+        }
+
+        let mut body = Reader { bytes: body };
+        let alphabet = body.take(padded(alphabet_len))?[..alphabet_len].to_vec();
+        if alphabet != charset() {
+            return Err(invalid("the dictionary was built for another charset"));
+        }
+        let masks = (0..n)
+            .map(|_| {
+                let words = [body.u64()?, body.u64()?, body.u64()?, body.u64()?];
+                Ok(ByteSet::from_words(words))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let values = (0..n)
+            .map(|_| {
+                Ok(Some(body.u64()?)
+                    .filter(|&v| v != NO_VALUE)
+                    .map(f64::from_bits))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let first_child = (0..n).map(|_| body.u32()).collect::<io::Result<Vec<_>>>()?;
+
+        // a checksum only catches accidents, make sure lookups can't go out of bounds
+        let inconsistent = || invalid("the dictionary's nodes are inconsistent");
+        let mut next = 1u32;
+        for (first, mask) in first_child.iter().zip(&masks) {
+            if *first != next {
+                return Err(inconsistent());
+            }
+            next = next
+                .checked_add(mask.len() as u32)
+                .ok_or_else(inconsistent)?;
+        }
+        if n == 0 || next as usize != n {
+            return Err(inconsistent());
+        }
+        // every path has to end in a word, the searches rely on it. only the root of an empty
+        // dictionary is neither
+        let dead_end = (1..n).any(|i| masks[i].is_empty() && values[i].is_none());
+        if dead_end {
+            return Err(invalid(
+                "the dictionary has a node that is neither a prefix nor a word",
+            ));
+        }
+
+        Ok(Self {
+            alphabet,
+            automaton: Automaton::from_parts(first_child, masks, values),
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dictionary;
+
+    #[test]
+    fn round_trips() {
+        let dict = CompiledDict::new([("the", 3.5), ("they", 5.0), ("yes", -2.0), ("x-ray", 1.0)]);
+        assert_eq!(dict.automaton.get("x-ray"), None);
+
+        let bytes = dict.to_bytes();
+        assert_eq!(bytes.len() % 8, 0);
+        let loaded = CompiledDict::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.alphabet, charset());
+        for (word, value) in [("the", Some(3.5)), ("they", Some(5.0)), ("yes", Some(-2.0))] {
+            assert_eq!(loaded.automaton.get(word), value);
+        }
+        assert_eq!(loaded.automaton.get("th"), None);
+        assert_eq!(loaded.automaton.node_count(), dict.automaton.node_count());
+
+        let mut corrupt = bytes.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert!(CompiledDict::from_bytes(&corrupt).is_err());
+        assert!(CompiledDict::from_bytes(&bytes[..bytes.len() - 8]).is_err());
+        assert!(CompiledDict::from_bytes(b"not a dictionary").is_err());
+
+        // a dictionary for other bytes, with a valid checksum
+        let mut other = dict.clone();
+        other.alphabet = b"abc".to_vec();
+        let err = CompiledDict::from_bytes(&other.to_bytes()).unwrap_err();
+        assert!(err.to_string().contains("charset"));
+
+        // "yes" isn't a word, but nothing continues it either
+        let (first_child, masks, values) = dict.automaton.parts();
+        let mut values = values.to_vec();
+        let yes = dict.automaton.walk("yes").unwrap();
+        values[yes as usize] = None;
+        let mut broken = dict.clone();
+        broken.automaton = Automaton::from_parts(first_child.to_vec(), masks.to_vec(), values);
+        assert!(CompiledDict::from_bytes(&broken.to_bytes()).is_err());
+        let empty = CompiledDict::new(Vec::<(&str, f64)>::new());
+        assert!(CompiledDict::from_bytes(&empty.to_bytes()).is_ok());
+    }
+}
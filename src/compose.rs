@@ -0,0 +1,299 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    contractions::Contractions,
+    morphology::Morphology,
+    score::{score_words, Scoring},
+    unknown::UnknownConfig,
+};
+
+/// the list used when a [`WordList`] has no path
+pub const BUILTIN_WORDS: &str = include_str!("./en_50k.txt");
+
+/// how to put a dictionary together from word lists, loaded from json so it can change without
+/// recompiling. the default is the built in english list of ascii words with at least two
+/// letters, plus "a" and without "th" and "ye"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DictConfig {
+    pub lists: Vec<WordList>,
+    /// words shorter than this are dropped unless they are included explicitly
+    pub min_len: usize,
+    pub max_len: Option<usize>,
+    pub ascii_only: bool,
+    /// words that are kept even if they break the length rules
+    pub include: BTreeSet<String>,
+    /// words that are always dropped
+    pub exclude: BTreeSet<String>,
+    /// words added on top of the lists, e.g. names or jargon of the target. they don't have to
+    /// keep to the length rules, but are dropped if they are excluded or not ascii
+    pub custom: Vec<CustomWord>,
+    /// adds inflected forms of the words, `{}` for the english ones
+    pub morphology: Option<Morphology>,
+    /// how the merged counts become the values of the dictionary
+    pub scoring: Scoring,
+    /// lets the searches take words that aren't in the dictionary, see
+    /// [`crate::unknown::UnknownWords`]
+    pub unknown: Option<UnknownConfig>,
+    /// adds forms like "don't" to the scored words, `{}` for the common english ones
+    pub contractions: Option<Contractions>,
+    /// lets hyphens join words into compounds like "two-time" at this cost, see
+    /// [`crate::compound::Compounds`]
+    pub compound_penalty: Option<f64>,
+}
+
+/// a file of `word count` lines. lines without a count count once
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WordList {
+    /// `None` is [`BUILTIN_WORDS`]
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// the counts of the list are multiplied by this before they are added up
+    #[serde(default = "crate::score::one")]
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomWord {
+    pub word: String,
+    /// `None` gives the word the count of the most frequent word of the lists
+    #[serde(default)]
+    pub count: Option<u64>,
+}
+
+impl Default for DictConfig {
+    fn default() -> Self {
+        Self {
+            lists: vec![WordList {
+                path: None,
+                weight: 1.0,
+            }],
+            min_len: 2,
+            max_len: None,
+            ascii_only: true,
+            include: ["a".to_owned()].into(),
+            exclude: ["th".to_owned(), "ye".to_owned()].into(),
+            custom: vec![],
+            morphology: None,
+            scoring: Scoring::default(),
+            unknown: None,
+            contractions: None,
+            compound_penalty: None,
+        }
+    }
+}
+
+impl DictConfig {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        serde_json::from_slice(&fs::read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// the built in list with only the words that aren't ascii dropped, which is how the
+    /// dictionary of the scored searches was always filtered
+    pub fn ascii_words() -> Self {
+        Self {
+            min_len: 0,
+            include: BTreeSet::new(),
+            exclude: BTreeSet::new(),
+            ..Default::default()
+        }
+    }
+
+    /// only the list at `path`, with the default rules
+    pub fn with_list(path: PathBuf) -> Self {
+        Self {
+            lists: vec![WordList {
+                path: Some(path),
+                weight: 1.0,
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn keep(&self, word: &str) -> bool {
+        if self.exclude.contains(word) || (self.ascii_only && !word.is_ascii()) {
+            return false;
+        }
+        let long_enough = word.len() >= self.min_len;
+        let short_enough = self.max_len.is_none_or(|max| word.len() <= max);
+        (long_enough && short_enough) || self.include.contains(word)
+    }
+
+    /// the words of all lists with their weighted counts, sorted by word
+    pub fn words(&self) -> io::Result<Vec<(String, u64)>> {
+        let texts = self
+            .lists
+            .iter()
+            .map(|list| match &list.path {
+                Some(path) => fs::read_to_string(path),
+                None => Ok(BUILTIN_WORDS.to_owned()),
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        self.merge(texts.iter().map(String::as_str))
+    }
+
+    /// the words of [`DictConfig::words`] with the values of [`DictConfig::scoring`], and their
+    /// contractions
+    pub fn scored_words(&self) -> io::Result<Vec<(String, f64)>> {
+        let words = self.words()?;
+        let scored = score_words(
+            &self.scoring,
+            words.iter().map(|(word, count)| (word.as_str(), *count)),
+        )
+        .into_iter()
+        .map(|(word, score)| (word.to_owned(), score))
+        .collect();
+        Ok(match &self.contractions {
+            Some(contractions) => contractions.apply(scored),
+            None => scored,
+        })
+    }
+
+    /// like [`DictConfig::words`], with the contents of the lists given in order. there has to be
+    /// one text per list
+    pub fn merge<'a>(
+        &self,
+        texts: impl IntoIterator<Item = &'a str>,
+    ) -> io::Result<Vec<(String, u64)>> {
+        let texts = texts.into_iter().collect::<Vec<_>>();
+        if texts.len() != self.lists.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "got {} texts for {} word lists",
+                    texts.len(),
+                    self.lists.len()
+                ),
+            ));
+        }
+
+        let mut counts = BTreeMap::<&str, f64>::new();
+        for (text, list) in texts.into_iter().zip(&self.lists) {
+            for line in text.lines() {
+                let mut parts = line.split_whitespace();
+                let Some(word) = parts.next() else {
+                    continue;
+                };
+                let count = parts.next().map_or(Some(1), |c| c.parse::<u64>().ok());
+                let Some(count) = count else {
+                    continue;
+                };
+                if self.keep(word) {
+                    *counts.entry(word).or_default() += count as f64 * list.weight;
+                }
+            }
+        }
+
+        let mut words = counts
+            .into_iter()
+            .map(|(word, count)| (word.to_owned(), count.round() as u64))
+            .filter(|&(_, count)| count > 0)
+            .collect::<BTreeMap<_, _>>();
+
+        let top = words.values().copied().max().unwrap_or(1);
+        for custom in &self.custom {
+            let ascii = !self.ascii_only || custom.word.is_ascii();
+            if ascii && !self.exclude.contains(&custom.word) {
+                words.insert(custom.word.clone(), custom.count.unwrap_or(top));
+            }
+        }
+
+        let Some(morphology) = &self.morphology else {
+            return Ok(words.into_iter().collect());
+        };
+        // the forms go through the same filters as the words of the lists
+        let forms = morphology.apply(words.clone().into_iter().collect());
+        Ok(forms
+            .into_iter()
+            .filter(|(word, _)| words.contains_key(word) || self.keep(word))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_weighted_lists() {
+        let config: DictConfig = serde_json::from_str(
+            r#"{
+                "lists": [{ "path": "a.txt" }, { "path": "b.txt", "weight": 0.5 }],
+                "exclude": ["th"],
+                "custom": [
+                    { "word": "xoracle" },
+                    { "word": "zz", "count": 3 },
+                    { "word": "na\u00efve" }
+                ],
+                "scoring": { "kind": "log-probability" },
+                "unknown": { "penalty": -5 },
+                "contractions": {},
+                "compound_penalty": -4
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.min_len, 2);
+        assert!(config.include.contains("a"));
+        assert_eq!(
+            config.scoring,
+            Scoring::LogProbability { length_bonus: 0.0 }
+        );
+        assert_eq!(config.contractions, Some(Contractions::default()));
+        assert_eq!(config.compound_penalty, Some(-4.0));
+        assert_eq!(
+            config.unknown,
+            Some(UnknownConfig {
+                max_len: 8,
+                penalty: -5.0
+            })
+        );
+
+        let words = config
+            .merge(["the 10\na 7\nb 5\nth 9\n", "the 8\ncat\ncaf\u{e9} 4\n"])
+            .unwrap();
+        assert_eq!(
+            words,
+            [
+                ("a", 7),
+                ("cat", 1),
+                ("the", 14),
+                ("xoracle", 14),
+                ("zz", 3)
+            ]
+            .map(|(w, c)| (w.to_owned(), c))
+        );
+
+        let inflected: DictConfig = serde_json::from_str(r#"{ "morphology": {} }"#).unwrap();
+        assert!(inflected
+            .merge(["cat 4"])
+            .unwrap()
+            .contains(&("cats".to_owned(), 1)));
+        let filtered: DictConfig =
+            serde_json::from_str(r#"{ "morphology": {}, "exclude": ["cats"], "max_len": 5 }"#)
+                .unwrap();
+        let words = filtered.merge(["cat 4"]).unwrap();
+        let words = words.iter().map(|(w, _)| w.as_str()).collect::<Vec<_>>();
+        assert_eq!(words, ["cat", "cated", "catly"]);
+        // a text is missing
+        assert!(config.merge(["the 10"]).is_err());
+
+        let words = DictConfig::ascii_words()
+            .merge(["the 10\na 7\nb 5\nth 9\ncaf\u{e9} 4\n"])
+            .unwrap();
+        assert_eq!(
+            words,
+            [("a", 7), ("b", 5), ("th", 9), ("the", 10)].map(|(w, c)| (w.to_owned(), c))
+        );
+
+        assert!(serde_json::from_str::<DictConfig>(r#"{ "lits": [] }"#).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use std::hash::Hasher;
+
+use crate::{byteset::ByteSet, dictionary::Fnv, Dictionary, NodeId, Queries, WordValue};
+
+/// a dictionary whose words may be joined by hyphens into compounds like "two-time" or "e-mail".
+/// every part scores like the word it is, and every hyphen adds `penalty`
+pub struct Compounds<'a, D: Dictionary> {
+    dict: &'a D,
+    penalty: D::Value,
+}
+
+impl<'a, D: Dictionary> Compounds<'a, D> {
+    pub fn new(dict: &'a D, penalty: D::Value) -> Self {
+        Self { dict, penalty }
+    }
+}
+
+impl<D: Dictionary<Value: WordValue>> Dictionary for Compounds<'_, D> {
+    type Value = D::Value;
+
+    fn root(&self) -> NodeId {
+        self.dict.root()
+    }
+
+    fn start(&self) -> Queries {
+        self.dict.start()
+    }
+
+    fn children(&self, node: NodeId) -> ByteSet {
+        self.dict.children(node)
+    }
+
+    fn child(&self, node: NodeId, label: u8) -> Option<NodeId> {
+        self.dict.child(node, label)
+    }
+
+    fn is_node(&self, node: NodeId) -> bool {
+        self.dict.is_node(node)
+    }
+
+    fn are_nodes(&self, nodes: impl IntoIterator<Item = NodeId>) -> bool {
+        self.dict.are_nodes(nodes)
+    }
+
+    fn value(&self, node: NodeId) -> Option<D::Value> {
+        self.dict.value(node)
+    }
+
+    fn is_word(&self, node: NodeId) -> bool {
+        self.dict.is_word(node)
+    }
+
+    fn has_children(&self, node: NodeId) -> bool {
+        self.dict.has_children(node)
+    }
+
+    fn compound_value(&self) -> Option<D::Value> {
+        Some(self.penalty)
+    }
+
+    /// differs from the dictionary's, as the searches go other ways with compounds
+    fn fingerprint(&self) -> u64 {
+        let mut hash = Fnv::default();
+        hash.write(b"compounds");
+        hash.write_u64(self.dict.fingerprint());
+        hash.write_u64(self.penalty.score().to_bits());
+        hash.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crack_all, crack_viterbi,
+        solver::{BestFirst, Solver},
+        xor_strings, Automaton, Solution, TokenKind,
+    };
+
+    #[test]
+    fn joins_words_with_hyphens() {
+        let dict: Automaton<f64> = [("two", 4.0), ("time", 5.0), ("pad", 6.0), ("the", 4.0)]
+            .into_iter()
+            .collect();
+        let cipher = xor_strings("two-time pad", "pad the time");
+        assert!(crack_viterbi(&cipher, &dict).is_none());
+
+        let compounds = Compounds::new(&dict, -2.0);
+        let best = crack_viterbi(&cipher, &compounds).unwrap();
+        let mut texts = [best.left.text.as_str(), best.right.text.as_str()];
+        texts.sort();
+        assert_eq!(texts, ["pad the time", "two-time pad"]);
+        assert_eq!(best.score, 4.0 + 5.0 + 6.0 - 2.0 + 6.0 + 4.0 + 5.0);
+        let joiner = [&best.left, &best.right]
+            .iter()
+            .flat_map(|text| &text.tokens)
+            .find(|t| t.kind == TokenKind::Joiner)
+            .copied();
+        assert_eq!(joiner.and_then(|t| t.value), Some(-2.0));
+
+        assert_ne!(compounds.fingerprint(), dict.fingerprint());
+        assert_ne!(
+            compounds.fingerprint(),
+            Compounds::new(&dict, -3.0).fingerprint()
+        );
+
+        let config = Default::default();
+        let found = BestFirst.solve(&cipher, &compounds, &config).solutions;
+        assert!(found.iter().any(|s| s.left.text == "two-time pad"));
+        let unscored: Automaton<()> = ["two", "time", "pad", "the"]
+            .map(|w| (w, ()))
+            .into_iter()
+            .collect();
+        let unscored = Compounds::new(&unscored, ());
+        assert!(crack_all(&cipher, &unscored).any(|s| s.right.text == "two-time pad"));
+
+        // a hyphen comes right after a word and is always followed by one
+        for left in ["pad -the", "pad,-the"] {
+            let cipher = xor_strings(left, "the time");
+            let found = |s: &Solution| s.left.text == left || s.right.text == left;
+            assert!(!crack_all(&cipher, &unscored).any(|s| found(&s)));
+            let solutions = BestFirst.solve(&cipher, &compounds, &config).solutions;
+            assert!(!solutions.iter().any(found));
+            assert!(crack_viterbi(&cipher, &compounds).is_none_or(|s| !found(&s)));
+        }
+        let cipher = xor_strings("two-time the pad", "pad the two-time");
+        let solutions = crack_all(&cipher, &unscored).collect::<Vec<_>>();
+        assert!(solutions.iter().any(|s| s.left.text.contains('-')));
+        for s in solutions {
+            for text in [s.left.text, s.right.text] {
+                let bytes = text.as_bytes();
+                let dangling = bytes.first() == Some(&b'-')
+                    || (bytes.windows(2)).any(|w| w[0] == b'-' && !w[1].is_ascii_lowercase())
+                    || (bytes.windows(2)).any(|w| w[1] == b'-' && !w[0].is_ascii_lowercase())
+                    || bytes.last() == Some(&b'-');
+                assert!(!dangling, "{text}");
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+/// adds contracted forms like "don't" or "cat's" to a dictionary, so that the apostrophe, which
+/// is a special char between words, can also be part of one. a contraction scores like the word
+/// it comes from plus a penalty
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Contractions {
+    pub rules: Vec<SuffixRule>,
+    /// contractions no rule makes, like "won't"
+    pub irregular: Vec<Irregular>,
+}
+
+/// appends `suffix` to words of the dictionary
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SuffixRule {
+    pub suffix: String,
+    /// the words that take the suffix, `None` for all of them
+    #[serde(default)]
+    pub words: Option<BTreeSet<String>>,
+    /// added to the score of the word
+    #[serde(default)]
+    pub penalty: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Irregular {
+    pub word: String,
+    /// the word whose score it gets, e.g. "will" for "won't"
+    pub like: String,
+    #[serde(default)]
+    pub penalty: f64,
+}
+
+const PRONOUNS: &[&str] = &[
+    "you", "we", "they", "he", "she", "it", "that", "there", "who",
+];
+const AUXILIARIES: &[&str] = &[
+    "do", "does", "did", "is", "are", "was", "were", "has", "have", "had", "could", "would",
+    "should", "must", "might", "need",
+];
+
+impl Default for Contractions {
+    /// the common english ones. "'s" goes on every word, as it may be a possessive
+    fn default() -> Self {
+        let rule = |suffix: &str, words: Option<&[&str]>, penalty| SuffixRule {
+            suffix: suffix.to_owned(),
+            words: words.map(|words| words.iter().map(|&w| w.to_owned()).collect()),
+            penalty,
+        };
+        let irregular = |word: &str, like: &str| Irregular {
+            word: word.to_owned(),
+            like: like.to_owned(),
+            penalty: -1.0,
+        };
+        Self {
+            rules: vec![
+                rule("'s", None, -2.0),
+                rule("n't", Some(AUXILIARIES), -1.0),
+                rule("'ll", Some(PRONOUNS), -1.0),
+                rule("'d", Some(PRONOUNS), -1.0),
+                rule("'re", Some(&["you", "we", "they", "who"]), -1.0),
+                rule(
+                    "'ve",
+                    Some(&["you", "we", "they", "who", "could", "would", "should"]),
+                    -1.0,
+                ),
+            ],
+            irregular: vec![
+                irregular("can't", "can"),
+                irregular("won't", "will"),
+                irregular("shan't", "shall"),
+                irregular("ain't", "is"),
+                irregular("i'm", "am"),
+                irregular("i'll", "will"),
+                irregular("i've", "have"),
+                irregular("i'd", "would"),
+                irregular("let's", "let"),
+            ],
+        }
+    }
+}
+
+impl Contractions {
+    /// `words` with the contractions of its words added. words that are in the list already keep
+    /// their score, rules and irregular contractions of words that aren't in it are skipped
+    pub fn apply(&self, words: Vec<(String, f64)>) -> Vec<(String, f64)> {
+        let mut res = words.iter().cloned().collect::<BTreeMap<_, _>>();
+        for rule in &self.rules {
+            let stems = words
+                .iter()
+                .filter(|(word, _)| rule.words.as_ref().is_none_or(|stems| stems.contains(word)));
+            for (stem, score) in stems {
+                res.entry(format!("{stem}{}", rule.suffix))
+                    .or_insert(score + rule.penalty);
+            }
+        }
+        let scores = words.into_iter().collect::<BTreeMap<_, _>>();
+        for irregular in &self.irregular {
+            if let Some(score) = scores.get(&irregular.like) {
+                res.entry(irregular.word.clone())
+                    .or_insert(score + irregular.penalty);
+            }
+        }
+        res.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{crack_viterbi, xor_strings, Automaton};
+
+    #[test]
+    fn adds_contractions() {
+        let words = [
+            ("do", 10.0),
+            ("will", 8.0),
+            ("cat", 6.0),
+            ("it", 5.0),
+            ("it's", 1.0),
+        ]
+        .map(|(w, s)| (w.to_owned(), s));
+        let plain: Automaton<f64> = words.clone().into_iter().collect();
+        let words = Contractions::default().apply(words.to_vec());
+        let score = |word: &str| words.iter().find(|(w, _)| w == word).map(|(_, s)| *s);
+        assert_eq!(score("don't"), Some(9.0));
+        assert_eq!(score("won't"), Some(7.0));
+        assert_eq!(score("cat's"), Some(4.0));
+        assert_eq!(score("it's"), Some(1.0));
+        assert_eq!(score("it'll"), Some(4.0));
+        assert_eq!(score("cat'll"), None);
+        assert_eq!(score("can't"), None);
+
+        let config: Contractions =
+            serde_json::from_str(r#"{ "rules": [{ "suffix": "'n" }], "irregular": [] }"#).unwrap();
+        assert_eq!(config.apply(vec![("rock".into(), 1.0)]).len(), 2);
+
+        // "t" and "s" aren't words, so this needs the contractions
+        let cipher = xor_strings("don't", "cat's");
+        assert!(crack_viterbi(&cipher, &plain).is_none());
+        let dict: Automaton<f64> = words.into_iter().collect();
+        let best = crack_viterbi(&cipher, &dict).unwrap();
+        let mut texts = [best.left.text, best.right.text];
+        texts.sort();
+        assert_eq!(texts, ["cat's", "don't"]);
+    }
+}
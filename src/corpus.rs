@@ -0,0 +1,156 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::{byteset::ByteSet, charset, special};
+
+/// word frequencies counted from raw text, e.g. to build a dictionary when there is no frequency
+/// list for the kind of text that was encrypted
+#[derive(Debug, Clone)]
+pub struct Corpus {
+    word_chars: ByteSet,
+    joiners: ByteSet,
+    counts: HashMap<String, u64>,
+}
+
+impl Default for Corpus {
+    /// words are runs of the charset's letters, apostrophes between letters are part of the word
+    /// as in "don't"
+    fn default() -> Self {
+        let special = ByteSet::from_bytes(special());
+        let letters = charset().iter().filter(|&&b| !special.contains(b));
+        Self::new(&letters.copied().collect::<Vec<_>>()).with_joiners(b"'")
+    }
+}
+
+impl Corpus {
+    /// words are maximal runs of `word_chars` after lowercasing ascii letters. other letters and
+    /// digits, including all non-ascii bytes, make the run they are in no word at all, so "café"
+    /// isn't counted as "caf". everything else separates words
+    pub fn new(word_chars: &[u8]) -> Self {
+        Self {
+            word_chars: ByteSet::from_bytes(word_chars),
+            joiners: ByteSet::EMPTY,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// `joiners` between two word chars don't separate them, like the apostrophe of "don't"
+    pub fn with_joiners(mut self, joiners: &[u8]) -> Self {
+        self.joiners = ByteSet::from_bytes(joiners);
+        self
+    }
+
+    pub fn add_text(&mut self, text: &[u8]) {
+        let lower = text.to_ascii_lowercase();
+        let word_chars = self.word_chars;
+        let is_foreign =
+            |b: u8| !word_chars.contains(b) && (!b.is_ascii() || b.is_ascii_alphanumeric());
+        let is_word_char = |i: usize| {
+            lower
+                .get(i)
+                .is_some_and(|&b| word_chars.contains(b) || is_foreign(b))
+        };
+        let mut start = None;
+        for i in 0..=lower.len() {
+            let joined = start.is_some()
+                && lower.get(i).is_some_and(|&b| self.joiners.contains(b))
+                && is_word_char(i + 1);
+            if is_word_char(i) || joined {
+                start.get_or_insert(i);
+            } else if let Some(start) = start.take() {
+                let word = &lower[start..i];
+                if !word.iter().any(|&b| is_foreign(b)) {
+                    self.count(word);
+                }
+            }
+        }
+    }
+
+    fn count(&mut self, word: &[u8]) {
+        let word = String::from_utf8_lossy(word);
+        match self.counts.get_mut(word.as_ref()) {
+            Some(count) => *count += 1,
+            None => {
+                self.counts.insert(word.into_owned(), 1);
+            }
+        }
+    }
+
+    pub fn add_file(&mut self, path: &Path) -> io::Result<()> {
+        self.add_text(&fs::read(path)?);
+        Ok(())
+    }
+
+    /// the number of distinct words
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// the words seen at least `min_count` times, most frequent first, and at most `max_words` of
+    /// them. ties are broken alphabetically, so the result doesn't depend on the order of the text
+    pub fn words(&self, min_count: u64, max_words: Option<usize>) -> Vec<(&str, u64)> {
+        let mut words = self
+            .counts
+            .iter()
+            .filter(|&(_, &count)| count >= min_count)
+            .map(|(word, &count)| (word.as_str(), count))
+            .collect::<Vec<_>>();
+        words.sort_by(|(a, x), (b, y)| y.cmp(x).then(a.cmp(b)));
+        words.truncate(max_words.unwrap_or(usize::MAX));
+        words
+    }
+}
+
+/// writes `word count` lines like those of `en_50k.txt`
+pub fn write_word_list<'a>(
+    words: impl IntoIterator<Item = (&'a str, u64)>,
+    mut out: impl Write,
+) -> io::Result<()> {
+    for (word, count) in words {
+        writeln!(out, "{word} {count}")?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_words() {
+        let mut corpus = Corpus::default();
+        corpus.add_text(b"The cat, the hat.\nTHE x-ray of 2 cats!");
+        corpus.add_text("the caf\u{e9} na\u{ef}ve r2d2".as_bytes());
+        corpus.add_text(b"don't 'em' o'");
+
+        assert_eq!(
+            corpus.words(1, None),
+            [
+                ("the", 4),
+                ("cat", 1),
+                ("cats", 1),
+                ("don't", 1),
+                ("em", 1),
+                ("hat", 1),
+                ("o", 1),
+                ("of", 1),
+                ("ray", 1),
+                ("x", 1)
+            ]
+        );
+        assert_eq!(corpus.words(2, None), [("the", 4)]);
+        assert_eq!(corpus.words(1, Some(2)), [("the", 4), ("cat", 1)]);
+
+        let mut out = vec![];
+        write_word_list(corpus.words(2, None), &mut out).unwrap();
+        assert_eq!(out, b"the 4\n");
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{product::Product, Dictionary, Solution};
+
+/// all pairs of segmentations consistent with a cipher. they are only counted up front, single
+/// solutions are reconstructed on demand, in the order of their bytes
+pub struct Segmentations<'a, D> {
+    product: Product<'a, D>,
+    // `counts[i][pair]` is the number of ways to finish the plain texts from `pair` in layer `i`.
+    // saturates at `u128::MAX`
+    counts: Vec<Vec<u128>>,
+}
+
+impl<'a, D: Dictionary<Value = f64>> Segmentations<'a, D> {
+    pub fn new(cipher: &'a [u8], root: &'a D) -> Self {
+        Self::of(Product::new(cipher, root))
+    }
+
+    /// the segmentations of a product that was already built
+    pub fn of(product: Product<'a, D>) -> Self {
+        let n = product.cipher.len();
+
+        let mut counts = vec![vec![]; n + 1];
+        counts[n] = product
+            .last()
+            .pairs
+            .iter()
+            .map(|pair| product.is_final(pair) as u128)
+            .collect();
+        for i in (0..n).rev() {
+            counts[i] = (0..product.layers[i].len())
+                .map(|from| {
+                    product.steps(i, from).iter().fold(0u128, |acc, step| {
+                        acc.saturating_add(counts[i + 1][step.to])
+                    })
+                })
+                .collect();
+        }
+
+        Self { product, counts }
+    }
+
+    pub fn count(&self) -> u128 {
+        self.counts[0][0]
+    }
+
+    /// the `k`-th solution
+    pub fn nth(&self, mut k: u128) -> Option<Solution> {
+        if k >= self.count() {
+            return None;
+        }
+
+        let n = self.product.cipher.len();
+        let mut steps = Vec::with_capacity(n);
+        let mut at = 0;
+        for i in 0..n {
+            let step = self
+                .product
+                .steps(i, at)
+                .into_iter()
+                .find(|step| {
+                    let count = self.counts[i + 1][step.to];
+                    if k < count {
+                        true
+                    } else {
+                        k -= count;
+                        false
+                    }
+                })
+                .expect("k is smaller than the number of solutions");
+            at = step.to;
+            steps.push(step);
+        }
+
+        Some(self.product.solution(&steps))
+    }
+
+    /// up to `limit` solutions, starting with the `offset`-th one
+    pub fn page(&self, offset: u128, limit: usize) -> Vec<Solution> {
+        (0..limit as u128)
+            .map_while(|i| self.nth(offset.checked_add(i)?))
+            .collect()
+    }
+}
+
+/// the number of pairs of segmentations consistent with `cipher`
+pub fn count_solutions(cipher: &[u8], root: &impl Dictionary<Value = f64>) -> u128 {
+    Segmentations::new(cipher, root).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture, xor_strings};
+
+    #[test]
+    fn counts_and_pages() {
+        let root = fixture::dict();
+        let cipher = fixture::cipher();
+
+        let solutions = Segmentations::new(&cipher, &root);
+        assert_eq!(solutions.count(), 4);
+
+        let all = solutions.page(0, 10);
+        assert_eq!(
+            all.iter()
+                .map(|s| (s.left.text.clone(), s.right.text.clone()))
+                .collect::<Vec<_>>(),
+            [
+                ("few", "kha"),
+                ("kha", "few"),
+                ("the", "yes"),
+                ("yes", "the")
+            ]
+            .map(|(a, b)| (a.to_owned(), b.to_owned()))
+        );
+        assert_eq!(solutions.page(1, 2), all[1..3]);
+        assert_eq!(solutions.nth(4), None);
+
+        assert_eq!(count_solutions(&xor_strings("yes", "thf"), &root), 0);
+    }
+}
@@ -0,0 +1,304 @@
+use serde::{Deserialize, Serialize};
+use trie_rs::inc_search::Answer;
+
+use crate::{
+    expand::Side, has_diverged, is_canonical, lookahead::Lookahead, CrackOptions, Dictionary,
+    ExpectedNext, NextState, NextStateExpected, PlainText, Queries, Solution, WordValue,
+};
+
+// a byte of a plain text, with the state of the side after it and the value of the dictionary
+// entry it reached
+type Event = (u8, ExpectedNext, Option<f64>);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Choice {
+    // the number of cipher bytes read once this choice is taken
+    depth: usize,
+    left: Side,
+    right: Side,
+    events: (Event, Event),
+    diverged: bool,
+}
+
+/// the depth-first search behind [`crate::crack`] and [`crate::crack_all`], yielding solutions as
+/// they are found. instead of recursing once per cipher byte it keeps the choices that are still
+/// to be tried on a stack, so its memory only grows with the length of the cipher times the number
+/// of choices per byte
+pub struct Search<'a, D> {
+    cipher: &'a [u8],
+    root: &'a D,
+    lookahead: Option<Lookahead>,
+    // whether both plain texts have to end with a whole word
+    whole_words: bool,
+    // whether solutions found while breaking symmetry are followed by their mirror image
+    mirror: bool,
+    pending: Option<Solution>,
+    // the next choice to try is on top
+    todo: Vec<Choice>,
+    // the events leading to the state that is currently explored
+    path: Vec<(Event, Event)>,
+    expanded: u64,
+}
+
+/// everything a depth-first search needs to continue later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Frontier {
+    whole_words: bool,
+    mirror: bool,
+    pending: Option<Solution>,
+    todo: Vec<Choice>,
+    path: Vec<(Event, Event)>,
+    expanded: u64,
+}
+
+impl Frontier {
+    /// whether the choices only point to nodes of `root`
+    pub fn belongs_to(&self, root: &impl Dictionary) -> bool {
+        root.are_nodes(
+            self.todo
+                .iter()
+                .flat_map(|c| c.left.queries.nodes().iter().chain(c.right.queries.nodes()))
+                .copied(),
+        )
+    }
+}
+
+impl<'a, D: Dictionary<Value: WordValue>> Search<'a, D> {
+    pub(crate) fn new(
+        cipher: &'a [u8],
+        root: &'a D,
+        t1: Queries,
+        t2: Queries,
+        options: CrackOptions,
+        whole_words: bool,
+    ) -> Self {
+        let lookahead = (options.lookahead > 0).then(|| Lookahead::new(root, options.lookahead));
+        // mirroring a solution only gives another one if both sides start out the same
+        let break_symmetry = options.break_symmetry && t1 == t2;
+        let mut search = Self {
+            cipher,
+            root,
+            lookahead,
+            whole_words,
+            mirror: !options.unordered && break_symmetry,
+            pending: None,
+            todo: vec![],
+            path: Vec::with_capacity(cipher.len()),
+            expanded: 0,
+        };
+        if !cipher.is_empty() {
+            search.push_choices(
+                0,
+                Side::new(t1, ExpectedNext::Word),
+                Side::new(t2, ExpectedNext::Word),
+                !break_symmetry,
+            );
+        }
+        search
+    }
+
+    pub(crate) fn resume(
+        cipher: &'a [u8],
+        root: &'a D,
+        options: CrackOptions,
+        frontier: Frontier,
+    ) -> Self {
+        let Frontier {
+            whole_words,
+            mirror,
+            pending,
+            todo,
+            path,
+            expanded,
+        } = frontier;
+        Self {
+            cipher,
+            root,
+            lookahead: (options.lookahead > 0).then(|| Lookahead::new(root, options.lookahead)),
+            whole_words,
+            mirror,
+            pending,
+            todo,
+            path,
+            expanded,
+        }
+    }
+
+    pub(crate) fn frontier(&self) -> Frontier {
+        Frontier {
+            whole_words: self.whole_words,
+            mirror: self.mirror,
+            pending: self.pending.clone(),
+            todo: self.todo.clone(),
+            path: self.path.clone(),
+            expanded: self.expanded,
+        }
+    }
+
+    /// the number of choices taken so far
+    pub fn expanded(&self) -> u64 {
+        self.expanded
+    }
+
+    // pushes the choices for the byte at `depth` so they are popped in the order of the charset,
+    // continuing a word before ending it
+    fn push_choices(&mut self, depth: usize, side1: Side, side2: Side, diverged: bool) {
+        let root = self.root;
+        let rest = &self.cipher[depth + 1..];
+        let start = self.todo.len();
+
+        let it1 = match side1.expected {
+            ExpectedNext::Word => NextStateExpected::Word(NextState::new(root, side1.queries)),
+            ExpectedNext::Special | ExpectedNext::AfterWord => NextStateExpected::Special {
+                i: 0,
+                root,
+                joinable: side1.expected == ExpectedNext::AfterWord,
+            },
+        };
+        for (ch1, ans1, val1, t1) in it1 {
+            let ch2 = self.cipher[depth] ^ ch1;
+            if !diverged && ch1 > ch2 {
+                continue;
+            }
+            if !side2.allowed(root).contains(ch2) {
+                continue;
+            }
+            let (ans2, val2, t2) = side2.step(root, ch2);
+            let (val1, val2) = (val1.map(|v| v.score()), val2.map(|v| v.score()));
+
+            let branches = |ans: Answer, expected: ExpectedNext| {
+                [
+                    ans.is_prefix().then_some(ExpectedNext::Word),
+                    ans.is_match().then_some(expected.after_match(root)),
+                ]
+                .into_iter()
+                .flatten()
+            };
+            for next1 in branches(ans1, side1.expected) {
+                for next2 in branches(ans2, side2.expected) {
+                    if !is_canonical(diverged, (ch1, next1), (ch2, next2)) {
+                        continue;
+                    }
+                    let (left, right) = (Side::new(t1, next1), Side::new(t2, next2));
+                    if let Some(lookahead) = &self.lookahead {
+                        if !lookahead.feasible(&left, &right, rest, self.whole_words) {
+                            continue;
+                        }
+                    }
+                    self.todo.push(Choice {
+                        depth: depth + 1,
+                        left,
+                        right,
+                        events: ((ch1, next1, val1), (ch2, next2, val2)),
+                        diverged: has_diverged(diverged, (ch1, next1), (ch2, next2)),
+                    });
+                }
+            }
+        }
+
+        self.todo[start..].reverse();
+    }
+
+    fn solution(&self) -> Solution {
+        Solution::new(
+            PlainText::from_events(self.path.iter().map(|&(left, _)| left)),
+            PlainText::from_events(self.path.iter().map(|&(_, right)| right)),
+        )
+    }
+
+    /// takes the next choice. `None` once the search is over, `Some(None)` if the choice didn't
+    /// complete a solution
+    pub(crate) fn advance(&mut self) -> Option<Option<Solution>> {
+        if let Some(mirrored) = self.pending.take() {
+            return Some(Some(mirrored));
+        }
+        if self.cipher.is_empty() {
+            // the empty texts have no whole words, they only count if words may be cut off
+            return (self.expanded == 0 && !self.whole_words).then(|| {
+                self.expanded = 1;
+                Some(Solution::default())
+            });
+        }
+
+        let choice = self.todo.pop()?;
+        self.expanded += 1;
+        self.path.truncate(choice.depth - 1);
+        self.path.push(choice.events);
+        if choice.depth == self.cipher.len() {
+            if self.whole_words && !(choice.left.is_final() && choice.right.is_final()) {
+                return Some(None);
+            }
+            let solution = self.solution();
+            if choice.diverged && self.mirror {
+                self.pending = Some(solution.mirrored());
+            }
+            return Some(Some(solution));
+        }
+        self.push_choices(choice.depth, choice.left, choice.right, choice.diverged);
+        Some(None)
+    }
+
+    /// the next plain texts that read the whole cipher. unless whole words are required, a word
+    /// may be cut off at the end
+    pub(crate) fn next_solution(&mut self) -> Option<Solution> {
+        loop {
+            if let Some(solution) = self.advance()? {
+                return Some(solution);
+            }
+        }
+    }
+}
+
+impl<D: Dictionary<Value: WordValue>> Iterator for Search<'_, D> {
+    type Item = Solution;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_solution()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        build_trie, charset, fixture, solver::BestFirst, xor, xor_strings, Automaton, Solver,
+    };
+
+    #[test]
+    fn long_ciphers_dont_overflow_the_stack() {
+        let root = Automaton::from(&build_trie(["yes", "the"].into_iter()));
+        let a = "yes the ".repeat(2000);
+        let b = "the yes ".repeat(2000);
+        let cipher = xor_strings(&a, &b);
+
+        let q = Queries::new(root.root());
+        let mut search = Search::new(&cipher, &root, q, q, CrackOptions::default(), false);
+        let solution = search.next_solution().expect("there is a solution");
+        assert_eq!(
+            xor(solution.left.text.bytes(), solution.right.text.bytes()),
+            cipher
+        );
+        // at most one set of choices per byte
+        assert!(search.todo.len() <= cipher.len() * charset().len() * 4);
+    }
+
+    #[test]
+    fn enumerates_whole_words() {
+        let root: Automaton<f64> = fixture::WORDS.into_iter().chain([("th", 1.0)]).collect();
+        let cipher = fixture::cipher();
+
+        let mut all = crate::crack_all(&cipher, &root)
+            .map(|s| (s.left.text, s.right.text))
+            .collect::<Vec<_>>();
+        all.sort();
+        let mut expected = BestFirst
+            .solve(&cipher, &root, &Default::default())
+            .solutions
+            .into_iter()
+            .map(|s| (s.left.text, s.right.text))
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(all, expected);
+        assert_eq!(crate::crack_all(&cipher, &root).take(2).count(), 2);
+    }
+}
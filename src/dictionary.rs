@@ -0,0 +1,318 @@
+use std::hash::Hasher;
+
+use louds_rs::LoudsNodeNum;
+use trie_rs::{
+    inc_search::{Answer, IncSearch},
+    map::Trie,
+};
+
+use crate::{byteset::ByteSet, NodeId, Queries, WordValue};
+
+/// a set of words with values that the searches walk one byte at a time. a node stands for a
+/// prefix of some words, its id only means something to the dictionary that handed it out
+pub trait Dictionary {
+    type Value: Copy;
+
+    fn root(&self) -> NodeId;
+
+    /// the nodes every word starts from
+    fn start(&self) -> Queries {
+        Queries::new(self.root())
+    }
+
+    /// the bytes that continue the prefix of `node`
+    fn children(&self, node: NodeId) -> ByteSet;
+
+    fn child(&self, node: NodeId, label: u8) -> Option<NodeId>;
+
+    /// whether `node` could have been handed out by this dictionary, so that nodes loaded from a
+    /// file can be checked before they are walked
+    fn is_node(&self, node: NodeId) -> bool;
+
+    /// whether all of `nodes` are nodes, for dictionaries that can check many at once faster
+    fn are_nodes(&self, nodes: impl IntoIterator<Item = NodeId>) -> bool {
+        nodes.into_iter().all(|node| self.is_node(node))
+    }
+
+    /// the value of the word spelled by `node`, if it is one
+    fn value(&self, node: NodeId) -> Option<Self::Value>;
+
+    fn is_word(&self, node: NodeId) -> bool {
+        self.value(node).is_some()
+    }
+
+    fn has_children(&self, node: NodeId) -> bool {
+        !self.children(node).is_empty()
+    }
+
+    fn answer(&self, node: NodeId) -> Option<Answer> {
+        match (self.has_children(node), self.is_word(node)) {
+            (true, false) => Some(Answer::Prefix),
+            (false, true) => Some(Answer::Match),
+            (true, true) => Some(Answer::PrefixAndMatch),
+            (false, false) => None,
+        }
+    }
+
+    fn walk(&self, word: impl AsRef<[u8]>) -> Option<NodeId> {
+        word.as_ref()
+            .iter()
+            .try_fold(self.root(), |node, &b| self.child(node, b))
+    }
+
+    fn get(&self, word: impl AsRef<[u8]>) -> Option<Self::Value> {
+        self.value(self.walk(word)?)
+    }
+
+    /// the value of a joiner from [`crate::joiners`] between two words, which makes a compound
+    /// like "two-time". `None` doesn't allow compounds. a joiner has to come right after a word
+    /// and be followed by one
+    fn compound_value(&self) -> Option<Self::Value> {
+        None
+    }
+
+    /// a hash of the words and their values, so that a saved search isn't continued with another
+    /// dictionary. there is no default, as one that always matches would let any dictionary
+    /// continue a search
+    fn fingerprint(&self) -> u64;
+}
+
+/// fnv-1a, which is quick to compute and good enough to tell dictionaries apart. it is also the
+/// checksum of [`crate::compiled::CompiledDict`]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// nodes are the positions of [`IncSearch`]. trie-rs can't list the children of a node, so
+/// [`Dictionary::children`] tries every byte and an [`crate::Automaton`] is a lot faster
+impl<V: Copy + WordValue> Dictionary for Trie<u8, V> {
+    type Value = V;
+
+    fn root(&self) -> NodeId {
+        LoudsNodeNum::from(self.inc_search()).0
+    }
+
+    fn children(&self, node: NodeId) -> ByteSet {
+        let search = IncSearch::resume(self, LoudsNodeNum(node));
+        (0..=255).filter(|b| search.peek(b).is_some()).collect()
+    }
+
+    fn child(&self, node: NodeId, label: u8) -> Option<NodeId> {
+        let mut search = IncSearch::resume(self, LoudsNodeNum(node));
+        search.query(&label)?;
+        Some(LoudsNodeNum::from(search).0)
+    }
+
+    fn value(&self, node: NodeId) -> Option<V> {
+        IncSearch::resume(self, LoudsNodeNum(node)).value().copied()
+    }
+
+    /// slow, see [`Dictionary::are_nodes`]
+    fn is_node(&self, node: NodeId) -> bool {
+        self.are_nodes([node])
+    }
+
+    /// louds numbers the root `1` and the other nodes, one per prefix of the words, after it.
+    /// trie-rs doesn't tell how many there are, so they are counted once from the words
+    fn are_nodes(&self, nodes: impl IntoIterator<Item = NodeId>) -> bool {
+        let mut words = self
+            .iter::<Vec<u8>, _>()
+            .map(|(word, _)| word)
+            .collect::<Vec<_>>();
+        words.sort();
+        // every word adds the prefixes it doesn't share with the one before it
+        let count = words
+            .iter()
+            .zip(std::iter::once(&vec![]).chain(&words))
+            .map(|(word, prev)| {
+                let shared = word.iter().zip(prev).take_while(|(a, b)| a == b).count();
+                (word.len() - shared) as NodeId
+            })
+            .sum::<NodeId>()
+            + 1;
+        nodes.into_iter().all(|node| (1..=count).contains(&node))
+    }
+
+    fn fingerprint(&self) -> u64 {
+        let mut hash = Fnv::default();
+        for (word, value) in self.iter::<Vec<u8>, _>() {
+            hash.write_usize(word.len());
+            hash.write(&word);
+            hash.write_u64(value.score().to_bits());
+        }
+        hash.finish()
+    }
+}
+
+/// a sorted list of words, for testing code against something that isn't a trie. a node is the
+/// length of a prefix in the upper 32 bits and the index of the first word with that prefix
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub(crate) struct WordSet {
+    words: Vec<Vec<u8>>,
+}
+
+#[cfg(test)]
+impl WordSet {
+    pub fn new<K: AsRef<[u8]>>(words: impl IntoIterator<Item = K>) -> Self {
+        let mut words = words
+            .into_iter()
+            .map(|w| w.as_ref().to_vec())
+            .collect::<Vec<_>>();
+        words.sort();
+        words.dedup();
+        Self { words }
+    }
+
+    // the prefix of `node` and the words that start with it
+    fn prefix(&self, node: NodeId) -> (&[u8], &[Vec<u8>]) {
+        let (len, first) = ((node >> 32) as usize, node as u32 as usize);
+        let prefix = &self.words[first][..len];
+        let count = self.words[first..].partition_point(|w| w.starts_with(prefix));
+        (prefix, &self.words[first..first + count])
+    }
+}
+
+#[cfg(test)]
+impl Dictionary for WordSet {
+    type Value = ();
+
+    fn root(&self) -> NodeId {
+        0
+    }
+
+    fn children(&self, node: NodeId) -> ByteSet {
+        if self.words.is_empty() {
+            return ByteSet::EMPTY;
+        }
+        let (prefix, words) = self.prefix(node);
+        words
+            .iter()
+            .filter_map(|w| w.get(prefix.len()))
+            .copied()
+            .collect()
+    }
+
+    fn child(&self, node: NodeId, label: u8) -> Option<NodeId> {
+        if self.words.is_empty() {
+            return None;
+        }
+        let (prefix, words) = self.prefix(node);
+        let first = node as u32 as usize;
+        let i = words
+            .iter()
+            .position(|w| w.get(prefix.len()) == Some(&label))?;
+        Some(((prefix.len() as NodeId + 1) << 32) | (first + i) as NodeId)
+    }
+
+    fn is_node(&self, node: NodeId) -> bool {
+        let (len, first) = ((node >> 32) as usize, node as u32 as usize);
+        if self.words.is_empty() || len == 0 {
+            return node == 0;
+        }
+        // `first` has to be the first word with the prefix
+        self.words.get(first).is_some_and(|word| {
+            word.len() >= len && (first == 0 || !self.words[first - 1].starts_with(&word[..len]))
+        })
+    }
+
+    fn value(&self, node: NodeId) -> Option<()> {
+        let first = node as u32 as usize;
+        let len = (node >> 32) as usize;
+        (self.words.get(first)?.len() == len).then_some(())
+    }
+
+    fn fingerprint(&self) -> u64 {
+        let mut hash = Fnv::default();
+        for word in &self.words {
+            hash.write_usize(word.len());
+            hash.write(word);
+        }
+        hash.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        all::build_trie_importance, build_trie, crack_all, solver::BestFirst, xor_strings,
+        Automaton, Solver,
+    };
+
+    fn check(dict: &impl Dictionary) {
+        let ye = dict.walk("ye").unwrap();
+        assert_eq!(dict.children(ye).iter().collect::<Vec<_>>(), b"as");
+        assert_eq!(dict.answer(ye), Some(Answer::Prefix));
+        assert_eq!(
+            dict.answer(dict.walk("y").unwrap()),
+            Some(Answer::PrefixAndMatch)
+        );
+        assert_eq!(dict.answer(dict.walk("year").unwrap()), Some(Answer::Match));
+        assert!(dict.walk("yeah").is_none());
+        assert!(dict.get("yes").is_some());
+        assert!(dict.get("yo").is_none());
+    }
+
+    #[test]
+    fn backends_agree() {
+        let words = ["yes", "year", "you", "y"];
+        let trie = build_trie(words.into_iter());
+        check(&trie);
+        check(&Automaton::from(&trie));
+        check(&WordSet::new(words));
+
+        let year = trie.walk("year").unwrap();
+        assert!(trie.is_node(year));
+        // the root and one node per prefix: y, ye, yo, yes, yea, you, year
+        assert!(trie.is_node(8) && !trie.is_node(9) && !trie.is_node(0));
+        assert!(trie.are_nodes([1, year, 8]) && !trie.are_nodes([1, 9]));
+        let set = WordSet::new(words);
+        assert!(set.is_node(set.walk("yea").unwrap()));
+        assert!(!set.is_node((1 << 32) | 2));
+
+        let fewer = build_trie(words[..3].iter().copied());
+        assert_ne!(trie.fingerprint(), fewer.fingerprint());
+        assert_eq!(
+            trie.fingerprint(),
+            build_trie(words.into_iter()).fingerprint()
+        );
+    }
+
+    #[test]
+    fn searches_take_any_backend() {
+        let words = ["yes", "the", "kha", "few"];
+        let cipher = xor_strings("yes", "the");
+        let automaton = Automaton::from(&build_trie(words.into_iter()));
+        let expected = crack_all(&cipher, &automaton).collect::<Vec<_>>();
+        assert_eq!(expected.len(), 4);
+        assert_eq!(
+            crack_all(&cipher, &WordSet::new(words)).collect::<Vec<_>>(),
+            expected
+        );
+
+        let scored = build_trie_importance(words.map(|w| (w, 2)).into_iter());
+        let config = Default::default();
+        assert_eq!(
+            BestFirst.solve(&cipher, &scored, &config),
+            BestFirst.solve(&cipher, &Automaton::from(&scored), &config)
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{
+    product::{Product, Step},
+    Dictionary, Solution,
+};
+
+#[derive(Debug, Clone, Copy)]
+struct Best {
+    score: f64,
+    // the pair in the previous layer and the step from there
+    back: Option<(usize, Step)>,
+}
+
+/// finds the pair of plain texts with the highest score, where the score is the sum of the values
+/// of all words in both plain texts. in contrast to [`crate::crack_non_rec`] this doesn't
+/// enumerate plain texts, but keeps only the best way to reach every pair of dictionary positions
+pub fn crack_viterbi(cipher: &[u8], root: &impl Dictionary<Value = f64>) -> Option<Solution> {
+    viterbi(&Product::new(cipher, root))
+}
+
+/// [`crack_viterbi`] over a product that was already built
+pub fn viterbi<D: Dictionary<Value = f64>>(product: &Product<D>) -> Option<Solution> {
+    let cipher = product.cipher;
+
+    let mut best = vec![vec![Best {
+        score: 0.0,
+        back: None,
+    }]];
+
+    for i in 0..cipher.len() {
+        let mut next: Vec<Option<Best>> = vec![None; product.layers[i + 1].len()];
+        for (from, cur) in best[i].iter().enumerate() {
+            for step in product.steps(i, from) {
+                let score = cur.score + step.score();
+                if next[step.to].is_none_or(|x| x.score < score) {
+                    next[step.to] = Some(Best {
+                        score,
+                        back: Some((from, step)),
+                    });
+                }
+            }
+        }
+        best.push(
+            next.into_iter()
+                .map(|x| x.expect("every pair of a layer has a predecessor"))
+                .collect(),
+        );
+    }
+
+    let (mut at, _) = product
+        .last()
+        .pairs
+        .iter()
+        .zip(&best[cipher.len()])
+        .enumerate()
+        .filter(|(_, (pair, _))| product.is_final(pair))
+        .map(|(i, (_, best))| (i, *best))
+        .max_by(|(_, a), (_, b)| a.score.total_cmp(&b.score))?;
+
+    let mut steps = Vec::with_capacity(cipher.len());
+    for layer in best.iter().rev() {
+        let Some((from, step)) = layer[at].back else {
+            break;
+        };
+        steps.push(step);
+        at = from;
+    }
+    steps.reverse();
+
+    Some(product.solution(&steps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture, xor_strings};
+
+    #[test]
+    fn finds_best_pair() {
+        let root = fixture::dict();
+        let cipher = fixture::cipher();
+
+        let best = crack_viterbi(&cipher, &root).expect("there is a solution");
+        assert_eq!(best.score, 5.0);
+        let pair = (best.left.text.as_str(), best.right.text.as_str());
+        assert!(pair == ("yes", "the") || pair == ("the", "yes"));
+
+        assert_eq!(crack_viterbi(&xor_strings("yes", "thf"), &root), None);
+    }
+}
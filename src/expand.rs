@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use trie_rs::inc_search::Answer;
+
+use crate::{byteset::ByteSet, charset, joiners, special, Dictionary, ExpectedNext, Queries};
+
+pub(crate) const CHARSET: ByteSet = ByteSet::from_bytes(charset());
+pub(crate) const SPECIAL: ByteSet = ByteSet::from_bytes(special());
+pub(crate) const JOINERS: ByteSet = ByteSet::from_bytes(joiners());
+
+/// the bytes a side may read right after a word
+pub(crate) fn separators(root: &impl Dictionary) -> ByteSet {
+    match root.compound_value() {
+        Some(_) => SPECIAL | JOINERS,
+        None => SPECIAL,
+    }
+}
+
+/// the bytes of the plain texts, which are the charset and the joiners if the dictionary allows
+/// compounds
+pub(crate) fn alphabet(root: &impl Dictionary) -> ByteSet {
+    match root.compound_value() {
+        Some(_) => CHARSET | JOINERS,
+        None => CHARSET,
+    }
+}
+
+/// for every cipher byte `c`, the bytes `a` of the charset for which `a ^ c` is in the charset as
+/// well
+#[derive(Debug, Clone)]
+pub struct PairMasks {
+    compatible: [ByteSet; 256],
+}
+
+impl PairMasks {
+    pub fn new(charset: ByteSet) -> Self {
+        Self {
+            compatible: std::array::from_fn(|c| charset & charset.xor(c as u8)),
+        }
+    }
+
+    pub fn compatible(&self, c: u8) -> ByteSet {
+        self.compatible[c as usize]
+    }
+
+    /// all pairs `(a, a ^ c)` with `a` in `left` and `a ^ c` in `right`
+    pub fn pairs(&self, c: u8, left: ByteSet, right: ByteSet) -> impl Iterator<Item = (u8, u8)> {
+        (self.compatible(c) & left & right.xor(c))
+            .iter()
+            .map(move |a| (a, a ^ c))
+    }
+}
+
+impl Default for PairMasks {
+    fn default() -> Self {
+        Self::new(CHARSET)
+    }
+}
+
+/// the position of one of the plain texts in the dictionary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub(crate) struct Side {
+    pub queries: Queries,
+    pub expected: ExpectedNext,
+}
+
+impl Side {
+    pub fn new(queries: Queries, expected: ExpectedNext) -> Self {
+        Self { queries, expected }
+    }
+
+    pub fn allowed(&self, root: &impl Dictionary) -> ByteSet {
+        match self.expected {
+            ExpectedNext::Word => self.queries.children(root) & CHARSET,
+            ExpectedNext::Special => SPECIAL,
+            ExpectedNext::AfterWord => separators(root),
+        }
+    }
+
+    /// `chr` has to be in `self.allowed(root)`
+    pub fn step<D: Dictionary>(&self, root: &D, chr: u8) -> (Answer, Option<D::Value>, Queries) {
+        match self.expected {
+            ExpectedNext::Word => {
+                let mut q = self.queries;
+                q.advance_all(root, chr);
+                let ans = q
+                    .answer(root)
+                    .expect("nodes reached by an edge are prefixes or matches");
+                (ans, q.value(root), q)
+            }
+            ExpectedNext::AfterWord if JOINERS.contains(chr) => {
+                (Answer::Prefix, root.compound_value(), root.start())
+            }
+            ExpectedNext::Special | ExpectedNext::AfterWord => {
+                (Answer::PrefixAndMatch, None, root.start())
+            }
+        }
+    }
+
+    /// every side reachable by reading `chr`, together with the value of the word or compound it
+    /// finished. sides expecting a special char always point at the root, so equal states compare
+    /// equal
+    pub fn next<D: Dictionary>(
+        &self,
+        root: &D,
+        chr: u8,
+    ) -> impl Iterator<Item = (Side, Option<D::Value>)> {
+        let (ans, value, queries) = self.step(root, chr);
+        let at_root = root.start();
+        // a word is finished by going on to expect a special char, a joiner counts right away
+        let (finished, joined) = match self.expected {
+            ExpectedNext::Word => (value, None),
+            ExpectedNext::Special | ExpectedNext::AfterWord => (None, value),
+        };
+
+        let special = ans.is_match().then_some((
+            Side::new(at_root, self.expected.after_match(root)),
+            finished,
+        ));
+        let word = ans
+            .is_prefix()
+            .then_some((Side::new(queries, ExpectedNext::Word), joined));
+        special.into_iter().chain(word)
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.expected != ExpectedNext::Word
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_valid_pairs() {
+        let masks = PairMasks::default();
+        let all = ByteSet::from_bytes(charset());
+
+        for c in 0..=255u8 {
+            let mut expected = charset()
+                .iter()
+                .filter(|&&a| charset().contains(&(a ^ c)))
+                .map(|&a| (a, a ^ c))
+                .collect::<Vec<_>>();
+            expected.sort();
+            assert_eq!(masks.pairs(c, all, all).collect::<Vec<_>>(), expected);
+        }
+
+        let left = ByteSet::from_bytes(b"ab");
+        let right = ByteSet::from_bytes(b"a");
+        assert_eq!(
+            masks.pairs(0, left, right).collect::<Vec<_>>(),
+            [(b'a', b'a')]
+        );
+        assert_eq!(
+            masks.pairs(3, left, right).collect::<Vec<_>>(),
+            [(b'b', b'a')]
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use std::{cmp::Ordering, collections::BTreeSet, fmt::Write};
+
+use serde::Serialize;
+
+use crate::{expand::Side, joiners, product::Product, Dictionary, ExpectedNext, TokenKind};
+
+/// a word or separator of a plain text spanning the bytes `start..end`
+#[derive(Debug, Clone, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub kind: TokenKind,
+    pub text: String,
+    pub score: f64,
+}
+
+impl PartialEq for Span {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Span {}
+
+impl PartialOrd for Span {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Span {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.start, self.end, self.kind, &self.text)
+            .cmp(&(other.start, other.end, other.kind, &other.text))
+            .then(self.score.total_cmp(&other.score))
+    }
+}
+
+/// all words and separators that are part of at least one solution. a path from offset `0` to
+/// offset `len` through the spans of one side isn't always a plain text, as it may splice spans
+/// of different solutions, and it ignores what the other side has to read at the same time
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Lattice {
+    pub len: usize,
+    pub left: Vec<Span>,
+    pub right: Vec<Span>,
+}
+
+// the words a side may be in the middle of in a pair, as the nodes of some dictionaries, like
+// transducers, don't tell which word led to them
+type Partial = BTreeSet<Vec<u8>>;
+
+// adds the span `side` finishes by reading `chr` at `end - 1`, and returns the partial words it
+// is in the middle of afterwards
+fn follow(
+    (side, next): (&Side, &Side),
+    partial: &Partial,
+    end: usize,
+    chr: u8,
+    value: Option<f64>,
+    spans: &mut BTreeSet<Span>,
+) -> Partial {
+    if side.expected != ExpectedNext::Word {
+        let joiner = joiners().contains(&chr);
+        spans.insert(Span {
+            start: end - 1,
+            end,
+            kind: if joiner {
+                TokenKind::Joiner
+            } else {
+                TokenKind::Separator
+            },
+            text: char::from(chr).to_string(),
+            score: if joiner { value.unwrap_or(0.0) } else { 0.0 },
+        });
+        return Partial::from([vec![]]);
+    }
+
+    let words = partial.iter().map(|word| {
+        let mut word = word.clone();
+        word.push(chr);
+        word
+    });
+    // a word only ends when the side goes on to expect a special char
+    match value {
+        Some(value) if next.is_final() => {
+            spans.extend(words.map(|word| Span {
+                start: end - word.len(),
+                end,
+                kind: TokenKind::Word,
+                text: String::from_utf8(word).expect("the charset is ascii"),
+                score: value,
+            }));
+            Partial::new()
+        }
+        _ => words.collect(),
+    }
+}
+
+impl Lattice {
+    pub fn new(cipher: &[u8], root: &impl Dictionary<Value = f64>) -> Self {
+        let product = Product::new(cipher, root);
+        let n = cipher.len();
+
+        // only pairs from which the plain texts can be finished contribute spans
+        let mut alive = vec![vec![]; n + 1];
+        alive[n] = product
+            .last()
+            .pairs
+            .iter()
+            .map(|pair| product.is_final(pair))
+            .collect();
+        for i in (0..n).rev() {
+            alive[i] = (0..product.layers[i].len())
+                .map(|from| product.steps(i, from).iter().any(|s| alive[i + 1][s.to]))
+                .collect();
+        }
+
+        let mut left = BTreeSet::new();
+        let mut right = BTreeSet::new();
+        let mut partial = vec![(Partial::from([vec![]]), Partial::from([vec![]]))];
+        for i in 0..n {
+            let mut next_partial =
+                vec![(Partial::new(), Partial::new()); product.layers[i + 1].len()];
+            for (from, (side1, side2)) in product.layers[i].pairs.iter().enumerate() {
+                if !alive[i][from] {
+                    continue;
+                }
+                let (partial1, partial2) = &partial[from];
+                for step in product.steps(i, from) {
+                    if !alive[i + 1][step.to] {
+                        continue;
+                    }
+                    let (next1, next2) = &product.layers[i + 1].pairs[step.to];
+                    let (ch1, ch2) = (step.ch1, step.ch2);
+                    let words1 = follow((side1, next1), partial1, i + 1, ch1, step.left, &mut left);
+                    let words2 =
+                        follow((side2, next2), partial2, i + 1, ch2, step.right, &mut right);
+                    next_partial[step.to].0.extend(words1);
+                    next_partial[step.to].1.extend(words2);
+                }
+            }
+            partial = next_partial;
+        }
+
+        Self {
+            len: n,
+            left: left.into_iter().collect(),
+            right: right.into_iter().collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("lattices are always serializable")
+    }
+
+    pub fn left_slf(&self) -> String {
+        slf(self.len, &self.left)
+    }
+
+    pub fn right_slf(&self) -> String {
+        slf(self.len, &self.right)
+    }
+}
+
+// htk's standard lattice format, with one node per byte offset and one link per span
+fn slf(len: usize, spans: &[Span]) -> String {
+    let mut res = String::new();
+    writeln!(res, "VERSION=1.0").unwrap();
+    writeln!(res, "start=0 end={len}").unwrap();
+    writeln!(res, "N={} L={}", len + 1, spans.len()).unwrap();
+    for i in 0..=len {
+        writeln!(res, "I={i} t={i}").unwrap();
+    }
+    for (j, span) in spans.iter().enumerate() {
+        let word = span
+            .text
+            .bytes()
+            .map(|b| {
+                if b.is_ascii_alphanumeric() {
+                    char::from(b).to_string()
+                } else {
+                    format!("\\{b:03o}")
+                }
+            })
+            .collect::<String>();
+        writeln!(
+            res,
+            "J={j} S={} E={} W={word} l={}",
+            span.start, span.end, span.score
+        )
+        .unwrap();
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compound::Compounds, transducer::FstDict, xor_strings, Automaton};
+
+    #[test]
+    fn spans_of_solutions() {
+        let words = [
+            ("a", 1.0),
+            ("at", 4.0),
+            ("is", 3.0),
+            ("to", 2.0),
+            ("it", 5.0),
+        ];
+        let root: Automaton<f64> = words.into_iter().collect();
+        let cipher = xor_strings("a at.", "is to");
+        let lattice = Lattice::new(&cipher, &root);
+
+        fn texts(spans: &[Span]) -> Vec<(usize, usize, &str)> {
+            spans
+                .iter()
+                .map(|s| (s.start, s.end, s.text.as_str()))
+                .collect()
+        }
+        assert!(texts(&lattice.left).contains(&(0, 1, "a")));
+        assert!(texts(&lattice.left).contains(&(2, 4, "at")));
+        assert!(texts(&lattice.right).contains(&(3, 5, "to")));
+        assert!(lattice.right.iter().all(|s| s.end <= 5));
+
+        let slf = lattice.left_slf();
+        assert!(slf.contains("N=6"));
+        assert!(slf.contains("S=1 E=2 W=\\040 l=0"));
+        assert!(lattice.to_json().contains("\"kind\": \"separator\""));
+
+        // transducers share the ends of words, so their nodes don't tell the words apart
+        assert_eq!(Lattice::new(&cipher, &FstDict::new(words)), lattice);
+
+        // the spans of "ab cd" and "a bcd" make "abbcd" on either side, which isn't a text
+        let root: Automaton<f64> = [("ab", 1.0), ("cd", 1.0), ("a", 1.0), ("bcd", 1.0)]
+            .into_iter()
+            .collect();
+        let lattice = Lattice::new(&xor_strings("ab cd", "a bcd"), &root);
+        assert!(texts(&lattice.left).contains(&(0, 2, "ab")));
+        assert!(texts(&lattice.left).contains(&(2, 5, "bcd")));
+
+        let compounds = Compounds::new(&root, -1.0);
+        let lattice = Lattice::new(&xor_strings("ab-cd", "a bcd"), &compounds);
+        let joiner = lattice.left.iter().find(|s| s.kind == TokenKind::Joiner);
+        assert_eq!(joiner.map(|s| (s.start, s.score)), Some((2, -1.0)));
+    }
+}
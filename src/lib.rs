@@ -1,17 +1,52 @@
 #![allow(clippy::too_many_arguments)]
 
 pub mod all;
-
-use trie_rs::{
-    inc_search::{Answer, IncSearch},
-    map::Trie,
-};
-
-pub use all::crack_non_rec;
+pub mod automaton;
+pub mod byteset;
+pub mod checkpoint;
+pub mod compiled;
+pub mod compose;
+pub mod compound;
+pub mod contractions;
+pub mod corpus;
+pub mod count;
+pub mod dfs;
+pub mod dictionary;
+pub mod dp;
+pub mod expand;
+pub mod lattice;
+pub mod lookahead;
+pub mod marginals;
+pub mod morphology;
+mod product;
+pub mod sample;
+pub mod score;
+pub mod solution;
+pub mod solver;
+pub mod transducer;
+pub mod unknown;
+
+use serde::{Deserialize, Serialize};
+use trie_rs::{inc_search::Answer, map::Trie};
+
+use crate::byteset::ByteSet;
+
+pub use all::{crack_non_rec, crack_non_rec_with};
+pub use automaton::{Automaton, NodeId, Queries, TooManyQueries};
+pub use dictionary::Dictionary;
+pub use dp::crack_viterbi;
+pub use product::Product;
+pub use solution::{PlainText, Solution, Token, TokenKind, WordValue};
+pub use solver::{Outcome, Solver, SolverConfig, Stats, Strategy};
 
 pub const fn special() -> &'static [u8] {
     b"'\" ,."
 }
+/// join two words into a compound if the dictionary allows it, see
+/// [`Dictionary::compound_value`]. they aren't in the charset, as no word is spelled with them
+pub const fn joiners() -> &'static [u8] {
+    b"-"
+}
 pub const fn charset() -> &'static [u8] {
     b"abcdefghijklmnopqrstuvwxyz'\" ,."
 }
@@ -72,419 +107,220 @@ pub fn xor_strings(a: &str, b: &str) -> Vec<u8> {
     a.bytes().zip(b.bytes()).map(|(a, b)| a ^ b).collect()
 }
 
-pub fn crack(
-    cipher: &[u8],
-    root: &Trie<u8, ()>,
-    t1: Queries<()>,
-    t2: Queries<()>,
-) -> Option<(Vec<u8>, Vec<u8>)> {
-    let mut res = crack_inner(
-        cipher,
-        root,
-        t1,
-        t2,
-        ExpectedNext::Word,
-        ExpectedNext::Word,
-        Default::default(),
-    )?;
-
-    res.0.reverse();
-    res.1.reverse();
-
-    Some(res)
+/// the error of a file that doesn't hold what it should, be it a dictionary or a checkpoint
+pub(crate) fn invalid(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, err)
 }
 
-#[derive(Clone, Debug)]
-pub struct Queries<'a, T> {
-    inner: Vec<IncSearch<'a, u8, T>>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrackOptions {
+    /// only explore solutions `(x, y)` where `x <= y`, as every solution `(x, y)` then also has
+    /// its mirror `(y, x)`. this only holds if both sides start from the same queries, so it is
+    /// ignored otherwise
+    pub break_symmetry: bool,
+    /// report `(x, y)` and `(y, x)` only once
+    pub unordered: bool,
+    /// drop states for which the next `lookahead` cipher bytes can't be matched by both sides.
+    /// `0` disables the check, more than [`lookahead::Lookahead::MAX_DEPTH`] bytes are cut to it
+    pub lookahead: usize,
 }
 
-impl<'a, T> Queries<'a, T> {
-    pub fn new(inner: IncSearch<'a, u8, T>) -> Self {
-        Self { inner: vec![inner] }
+impl Default for CrackOptions {
+    fn default() -> Self {
+        Self {
+            break_symmetry: true,
+            unordered: false,
+            lookahead: 0,
+        }
     }
+}
 
-    pub fn advance_all(&mut self, q: u8) {
-        let inner = std::mem::take(&mut self.inner);
-        self.inner = inner
-            .into_iter()
-            .filter_map(|mut x| if x.query(&q).is_some() { Some(x) } else { None })
-            .collect()
-    }
+pub fn crack<D: Dictionary<Value: WordValue>>(
+    cipher: &[u8],
+    root: &D,
+    t1: Queries,
+    t2: Queries,
+) -> Option<Solution> {
+    crack_with(cipher, root, t1, t2, CrackOptions::default())
+}
+
+pub fn crack_with<D: Dictionary<Value: WordValue>>(
+    cipher: &[u8],
+    root: &D,
+    t1: Queries,
+    t2: Queries,
+    options: CrackOptions,
+) -> Option<Solution> {
+    dfs::Search::new(cipher, root, t1, t2, options, false).next_solution()
+}
+
+/// all solutions of the depth-first search of [`crack`], found one at a time. unlike
+/// [`crack_non_rec`] it doesn't keep a heap of states, so it needs little memory, but it finds
+/// the solutions in the order of the charset instead of by importance. in contrast to [`crack`]
+/// both plain texts have to end with a whole word
+pub fn crack_all<'a, D: Dictionary<Value: WordValue>>(
+    cipher: &'a [u8],
+    root: &'a D,
+) -> dfs::Search<'a, D> {
+    crack_all_with(cipher, root, CrackOptions::default())
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub fn crack_all_with<'a, D: Dictionary<Value: WordValue>>(
+    cipher: &'a [u8],
+    root: &'a D,
+    options: CrackOptions,
+) -> dfs::Search<'a, D> {
+    let q = root.start();
+    dfs::Search::new(cipher, root, q, q, options, true)
+}
+
+// the order is only used to pick a canonical side when breaking symmetry
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
 enum ExpectedNext {
     Word,
     Special,
+    // a word just ended, so a joiner may come as well as a special char. only used if the
+    // dictionary allows compounds
+    AfterWord,
 }
 
-#[derive(Debug, Clone)]
-struct NextState<'a, T> {
+impl ExpectedNext {
+    // what a side expects after reading a byte that ended a word or a run of special chars
+    fn after_match(self, root: &impl Dictionary) -> Self {
+        match self {
+            Self::Word if root.compound_value().is_some() => Self::AfterWord,
+            _ => Self::Special,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct NextState<'a, D> {
     charset_idx: usize,
-    q_idx: usize,
-    did_produce: [bool; 256],
-    q: Queries<'a, T>,
+    next: ByteSet,
+    dict: &'a D,
+    q: Queries,
 }
 
-impl<'a, T> NextState<'a, T> {
-    fn new(q: Queries<'a, T>) -> Self {
+// manual impls, as the derives would require `D: Clone`
+impl<D> Clone for NextState<'_, D> {
+    fn clone(&self) -> Self {
+        Self { ..*self }
+    }
+}
+
+impl<'a, D: Dictionary> NextState<'a, D> {
+    fn new(dict: &'a D, q: Queries) -> Self {
         Self {
             charset_idx: 0,
-            q_idx: 0,
-            did_produce: [false; 256],
+            next: q.children(dict),
+            dict,
             q,
         }
     }
 }
 
-impl<'a, T> Iterator for NextState<'a, T>
-where
-    T: Clone,
-{
-    type Item = (u8, Answer, Option<&'a T>, Queries<'a, T>);
+impl<D: Dictionary> Iterator for NextState<'_, D> {
+    type Item = (u8, Answer, Option<D::Value>, Queries);
 
     fn next(&mut self) -> Option<Self::Item> {
         while self.charset_idx < charset().len() {
             let chr = charset()[self.charset_idx];
-            while self.q_idx < self.q.inner.len() && !self.did_produce[chr as usize] {
-                let Some(ans) = self.q.inner[self.q_idx].peek(&chr) else {
-                    self.q_idx += 1;
-                    continue;
-                };
-                let mut tmp = self.q.inner[self.q_idx].clone();
-                tmp.query(&chr);
-                let mut q = self.q.clone();
-
-                q.advance_all(chr);
-
-                self.did_produce[chr as usize] = true;
-                self.q_idx += 1;
-                return Some((chr, ans, tmp.value(), q));
+            self.charset_idx += 1;
+            if !self.next.contains(chr) {
+                continue;
             }
 
-            self.q_idx = 0;
-            self.charset_idx += 1;
+            let mut q = self.q;
+            q.advance_all(self.dict, chr);
+            let ans = q
+                .answer(self.dict)
+                .expect("nodes reached by an edge are prefixes or matches");
+            return Some((chr, ans, q.value(self.dict), q));
         }
 
         None
     }
 }
 
-#[derive(Clone)]
-enum NextStateExpected<'a, T> {
-    Word(Box<NextState<'a, T>>),
-    Special { i: usize, root: &'a Trie<u8, T> },
-    // WordOrSpecial {
-    //     i: usize,
-    //     root: &'b Trie<u8>,
-    //     next: NextState<'a>,
-    // },
+enum NextStateExpected<'a, D> {
+    Word(NextState<'a, D>),
+    // `joinable` after a word, see `ExpectedNext::AfterWord`
+    Special {
+        i: usize,
+        root: &'a D,
+        joinable: bool,
+    },
 }
 
-impl<'a, T> Iterator for NextStateExpected<'a, T>
-where
-    T: Clone,
-{
-    type Item = (u8, Answer, Option<&'a T>, Queries<'a, T>);
+impl<D> Clone for NextStateExpected<'_, D> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Word(next) => Self::Word(next.clone()),
+            Self::Special { i, root, joinable } => Self::Special {
+                i: *i,
+                root,
+                joinable: *joinable,
+            },
+        }
+    }
+}
+
+impl<D: Dictionary> Iterator for NextStateExpected<'_, D> {
+    type Item = (u8, Answer, Option<D::Value>, Queries);
 
     fn next(&mut self) -> Option<Self::Item> {
         match self {
             NextStateExpected::Word(i) => i.next(),
-            NextStateExpected::Special { i, root } => {
-                if *i >= special().len() {
-                    return None;
+            NextStateExpected::Special { i, root, joinable } => {
+                if let Some(&chr) = special().get(*i) {
+                    *i += 1;
+                    return Some((chr, Answer::PrefixAndMatch, None, root.start()));
                 }
-                let chr = special()[*i];
+                // a joiner has to come right after a word and be followed by one
+                let value = root.compound_value().filter(|_| *joinable)?;
+                let &chr = joiners().get(*i - special().len())?;
                 *i += 1;
-                Some((
-                    chr,
-                    Answer::PrefixAndMatch,
-                    None,
-                    Queries::new(root.inc_search()),
-                ))
+                Some((chr, Answer::Prefix, Some(value), root.start()))
             }
         }
     }
 }
 
-fn try_1(
-    cipher: &[u8],
-    root: &Trie<u8, ()>,
-    t1: Queries<()>,
-    expected_next1: ExpectedNext,
-    ch1: u8,
-    ch2: u8,
-    it2: NextStateExpected<()>,
-    (h1, h2): (String, String),
-) -> Option<(Vec<u8>, Vec<u8>)> {
-    for (_, ans, _, t2) in it2.clone().filter(|(ch, _, _, _)| (*ch == ch2)) {
-        if ans.is_prefix() {
-            // t2.inner.push(root.inc_search());
-            // eprintln!("{h2:?} t2 is prefix");
-            if let Some(x) = try_2(
-                cipher,
-                root,
-                t1.clone(),
-                t2.clone(),
-                expected_next1,
-                ExpectedNext::Word,
-                ch1,
-                ch2,
-                (h1.clone(), h2.clone()),
-            ) {
-                return Some(x);
-            }
-        };
-
-        if ans.is_match() {
-            // t2.inner.push(root.inc_search());
-            // eprintln!("{h2:?} t2 is match");
-            if let Some(x) = try_2(
-                cipher,
-                root,
-                t1.clone(),
-                t2.clone(),
-                expected_next1,
-                ExpectedNext::Special,
-                ch1,
-                ch2,
-                (h1.clone(), h2.clone()),
-            ) {
-                return Some(x);
-            }
-        };
-
-        assert!(ans.is_match() || ans.is_prefix());
-    }
-
-    None
+// while both sides are still identical, only the pair of steps with `(ch1, next1) <= (ch2, next2)`
+// is explored, as its mirror image leads to the mirrored solutions
+fn is_canonical(
+    diverged: bool,
+    (ch1, next1): (u8, ExpectedNext),
+    (ch2, next2): (u8, ExpectedNext),
+) -> bool {
+    diverged || (ch1, next1) <= (ch2, next2)
 }
 
-fn try_2(
-    cipher: &[u8],
-    root: &Trie<u8, ()>,
-    t1: Queries<()>,
-    t2: Queries<()>,
-    expected_next1: ExpectedNext,
-    expected_next2: ExpectedNext,
-    ch1: u8,
-    ch2: u8,
-    (h1, h2): (String, String),
-) -> Option<(Vec<u8>, Vec<u8>)> {
-    let (mut a, mut b) = crack_inner(
-        cipher,
-        root,
-        t1.clone(),
-        t2,
-        expected_next1,
-        expected_next2,
-        (
-            format!("{h1}{}", ch1 as char),
-            format!("{h2}{}", ch2 as char),
-        ),
-    )?;
-
-    // eprintln!(
-    //     "{:?} {:?}",
-    //     std::str::from_utf8(&a.iter().rev().copied().collect::<Vec<_>>()).unwrap(),
-    //     std::str::from_utf8(&b.iter().rev().copied().collect::<Vec<_>>()).unwrap()
-    // );
-
-    a.push(ch1);
-    b.push(ch2);
-    Some((a, b))
+fn has_diverged(
+    diverged: bool,
+    (ch1, next1): (u8, ExpectedNext),
+    (ch2, next2): (u8, ExpectedNext),
+) -> bool {
+    diverged || ch1 != ch2 || next1 != next2
 }
 
-fn crack_inner(
-    cipher: &[u8],
-    root: &Trie<u8, ()>,
-    t1: Queries<()>,
-    t2: Queries<()>,
-    expected_next1: ExpectedNext,
-    expected_next2: ExpectedNext,
-    (h1, h2): (String, String),
-) -> Option<(Vec<u8>, Vec<u8>)> {
-    if cipher.is_empty() {
-        return Some(Default::default());
-    }
-
-    // let mut choice_set = NextCharSetIter::new(t1.clone());
-
-    let it1 = match expected_next1 {
-        ExpectedNext::Word => NextStateExpected::Word(Box::new(NextState::new(t1))),
-        ExpectedNext::Special => NextStateExpected::Special { i: 0, root },
-    };
-
-    let it2 = match expected_next2 {
-        ExpectedNext::Word => NextStateExpected::Word(Box::new(NextState::new(t2))),
-        ExpectedNext::Special => NextStateExpected::Special { i: 0, root },
-    };
-
-    // let it1 = Box::new(NextState::new(t1.clone()));
-    // let it2: Box<dyn Iterator<Item = _>> = Box::new(NextState::new(t2.clone()));
+/// the small dictionary and cipher most tests search
+#[cfg(test)]
+pub(crate) mod fixture {
+    use crate::{xor_strings, Automaton};
 
-    for (ch1, ans, _, t1) in it1 {
-        let ch2 = cipher[0] ^ ch1;
-        // eprintln!(
-        //     "'{h1}{}' '{h2}{}' \t\t{} {ch1} {ch2}",
-        //     ch1 as char, ch2 as char, cipher[0],
-        // );
+    /// "yes" and "the" score highest, "kha" and "few" are the other pair that fits [`cipher`]
+    pub const WORDS: [(&str, f64); 4] = [("the", 3.0), ("yes", 2.0), ("kha", 1.0), ("few", 1.0)];
 
-        if ans.is_prefix() {
-            if let Some(x) = try_1(
-                &cipher[1..],
-                root,
-                t1.clone(),
-                ExpectedNext::Word,
-                ch1,
-                ch2,
-                it2.clone(),
-                (h1.clone(), h2.clone()),
-            ) {
-                return Some(x);
-            }
-        }
-        if ans.is_match() {
-            if let Some(x) = try_1(
-                &cipher[1..],
-                root,
-                t1.clone(),
-                ExpectedNext::Special,
-                ch1,
-                ch2,
-                it2.clone(),
-                (h1.clone(), h2.clone()),
-            ) {
-                return Some(x);
-            }
-        }
-        assert!(ans.is_match() || ans.is_prefix());
-
-        // if h1 == "ye" && ch1 == b's' {
-        //     for i in it2.clone() {
-        //         eprint!("({} {}) \t", i.0 as char, i.1)
-        //     }
-        //     eprintln!()
-        // }
-
-        // for (_, ans, mut t2) in it2.clone().filter(|(ch, _, _)| (*ch == ch2)) {
-        //     if ans.is_match() {
-        //         t2.inner.push(root.inc_search());
-        //         // eprintln!("t2 is match");
-        //         if let Some(x) = try_2(
-        //             &cipher[1..],
-        //             root,
-        //             t1.clone(),
-        //             t2.clone(),
-        //             expected_next1,
-        //             ExpectedNext::Special,
-        //             ch1,
-        //             ch2,
-        //             (h1.clone(), h2.clone()),
-        //         ) {
-        //             return Some(x);
-        //         }
-        //     };
-        //
-        //     if ans.is_prefix() {
-        //         t2.inner.push(root.inc_search());
-        //         // eprintln!("t2 is match");
-        //         if let Some(x) = try_2(
-        //             &cipher[1..],
-        //             root,
-        //             t1.clone(),
-        //             t2,
-        //             expected_next1,
-        //             ExpectedNext::Word,
-        //             ch1,
-        //             ch2,
-        //             (h1.clone(), h2.clone()),
-        //         ) {
-        //             return Some(x);
-        //         }
-        //     };
-        //
-        //     assert!(ans.is_match() || ans.is_prefix());
-        // }
+    pub fn dict() -> Automaton<f64> {
+        WORDS.into_iter().collect()
     }
-    // return None;
 
-    // for (a, b) in t1.inner.iter().zip(t2.inner.iter()) {
-    //     if let Some(x) = charset().iter().find_map(|t1_ch| {
-    //         let mut a = a.clone();
-    //         let t1_ans = a.query(t1_ch)?;
-    //
-    //         let mut t1 = t1.clone();
-    //         t1.advance_all(*t1_ch);
-    //
-    //         // eprintln!("{} {cipher:02x?}", *t1_ch as char);
-    //         if cipher.len() == 1 && !t1_ans.is_match() {
-    //             return None;
-    //         }
-    //
-    //         let expected_next1 = if t1_ans.is_match() {
-    //             t1.inner.push(root.inc_search());
-    //             ExpectedNext::SpecialOrWord
-    //         } else {
-    //             ExpectedNext::Word
-    //         };
-    //
-    //         let expected = cipher[0] ^ t1_ch;
-    //
-    //         let mut b = b.clone();
-    //         let t2_ans = b.query(&expected)?;
-    //
-    //         let mut t2 = t2.clone();
-    //         t2.advance_all(expected);
-    //
-    //         if cipher.len() == 1 && !t2_ans.is_match() {
-    //             return None;
-    //         }
-    //
-    //         let expected_next2 = if t2_ans.is_match() {
-    //             t2.inner.push(root.inc_search());
-    //             ExpectedNext::SpecialOrWord
-    //         } else {
-    //             ExpectedNext::Word
-    //         };
-    //
-    //         let (mut a, mut b) =
-    //             crack_inner(&cipher[1..], root, t1, t2, expected_next1, expected_next2)?;
-    //
-    //         a.push(*t1_ch);
-    //         b.push(expected);
-    //         Some((a, b))
-    //     }) {
-    //         return Some(x);
-    //     }
-    // }
-
-    None
-
-    // charset().iter().find_map(|t1_ch| {
-    //     let mut t1 = t1.clone();
-    //     let t1_ans = t1.query(t1_ch)?;
-    //
-    //     // eprintln!("{} {cipher:02x?}", *t1_ch as char);
-    //     if cipher.len() == 1 && !t1_ans.is_match() {
-    //         return None;
-    //     }
-    //
-    //     let expected = cipher[0] ^ t1_ch;
-    //     let mut t2 = t2.clone();
-    //     let t2_ans = t2.query(&expected)?;
-    //
-    //     if cipher.len() == 1 && !t2_ans.is_match() {
-    //         return None;
-    //     }
-    //
-    //     let (mut a, mut b) = crack_inner(&cipher[1..], _root, t1, t2)?;
-    //
-    //     a.push(*t1_ch);
-    //     b.push(expected);
-    //     Some((a, b))
-    // })
+    /// four solutions over [`dict`]: "yes"/"the", "kha"/"few" and their mirrors
+    pub fn cipher() -> Vec<u8> {
+        xor_strings("yes", "the")
+    }
 }
 
 #[cfg(test)]
@@ -492,16 +328,13 @@ mod tests {
     use super::*;
     #[test]
     fn next_state_iterator() {
-        let trie = build_trie(["yes", "year", "you", "cyan"].iter().cloned());
+        let trie = Automaton::from(&build_trie(["yes", "year", "you", "cyan"].iter().cloned()));
 
-        let mut cy = trie.inc_search();
-        cy.query(&b'c');
+        let cy = trie.walk("c").unwrap();
 
-        let q = Queries {
-            inner: vec![trie.inc_search(), cy],
-        };
+        let q = Queries::from_nodes([trie.root(), cy]).unwrap();
 
-        let mut states = NextState::new(q).map(|x| x.0).collect::<Vec<_>>();
+        let mut states = NextState::new(&trie, q).map(|x| x.0).collect::<Vec<_>>();
         states.sort();
 
         assert_eq!(states, [b'c', b'y']);
@@ -509,24 +342,69 @@ mod tests {
 
     #[test]
     fn next_state_iterator_advances_correctly() {
-        let trie = build_trie(["yes", "year", "you", "cyan"].iter().cloned());
+        let trie = Automaton::from(&build_trie(["yes", "year", "you", "cyan"].iter().cloned()));
 
-        let mut cy = trie.inc_search();
-        cy.query(&b'c');
+        let cy = trie.walk("c").unwrap();
 
-        let q = Queries {
-            inner: vec![trie.inc_search(), cy],
-        };
+        let q = Queries::from_nodes([trie.root(), cy]).unwrap();
 
-        let (_, ans, _, new_q) = NextState::new(q)
+        let (_, ans, _, new_q) = NextState::new(&trie, q)
             .find(|x| x.0 == b'y')
             .expect("y is a valid next char");
 
         assert!(!ans.is_match());
 
-        let mut states = NextState::new(new_q).map(|x| x.0).collect::<Vec<_>>();
+        let mut states = NextState::new(&trie, new_q)
+            .map(|x| x.0)
+            .collect::<Vec<_>>();
         states.sort();
 
         assert_eq!(states, [b'a', b'e', b'o']);
     }
+
+    #[test]
+    fn crack_breaks_symmetry() {
+        let trie = Automaton::from(&build_trie(["yes", "the"].iter().cloned()));
+        let cipher = xor_strings("yes", "the");
+
+        let q = || Queries::new(trie.root());
+        let res = crack(&cipher, &trie, q(), q()).expect("there is a solution");
+        assert_eq!(
+            (res.left.text.as_str(), res.right.text.as_str()),
+            ("the", "yes")
+        );
+        assert_eq!(res.left.words().collect::<Vec<_>>(), ["the"]);
+
+        let options = CrackOptions {
+            break_symmetry: false,
+            ..Default::default()
+        };
+        let res = crack_with(&cipher, &trie, q(), q(), options).expect("there is a solution");
+        assert_eq!(xor(res.left.text.bytes(), res.right.text.bytes()), cipher);
+
+        // the right side continues "yes", so its solutions have no mirror image
+        let trie = Automaton::from(&build_trie(["yes", "the", "es"].iter().cloned()));
+        let y = Queries::new(trie.walk("y").unwrap());
+        let res = crack(&xor_strings("th", "es"), &trie, q(), y).expect("there is a solution");
+        assert_eq!(
+            (res.left.text.as_str(), res.right.text.as_str()),
+            ("th", "es")
+        );
+    }
+
+    #[test]
+    fn lookahead_keeps_cut_off_words() {
+        let trie = fixture::dict();
+        let cipher = xor_strings("yes th", "the ye");
+        let q = trie.start();
+        let res = |lookahead| {
+            let options = CrackOptions {
+                lookahead,
+                ..Default::default()
+            };
+            crack_with(&cipher, &trie, q, q, options).map(|s| (s.left.text, s.right.text))
+        };
+        assert!(res(0).is_some());
+        assert_eq!(res(3), res(0));
+    }
 }
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use crate::{
+    byteset::ByteSet,
+    expand::{separators, Side, CHARSET},
+    Dictionary, ExpectedNext, NodeId,
+};
+
+/// over-approximates which bytes a side can produce in the next `depth` steps, so that states
+/// whose continuations can't possibly match the upcoming cipher bytes are dropped early
+#[derive(Debug, Clone)]
+pub struct Lookahead {
+    depth: usize,
+    // the nodes reachable from the root through the charset, numbered in bfs order. the tables
+    // below are indexed by these numbers, nodes that aren't in here are never ruled out
+    index: Index,
+    // `word[i * depth + d]` are the bytes possible `d` steps after expecting a word at node `i`
+    word: Vec<ByteSet>,
+    // `special[d]` are the bytes possible `d` steps after expecting a special char
+    special: Vec<ByteSet>,
+    // the minimal number of bytes needed to finish the word at node `i`
+    min_end: Vec<u32>,
+    // bit `d` is set if the word at node `i` can be finished after exactly `d` bytes
+    ends: Vec<u64>,
+}
+
+/// the numbers of the nodes of a [`Lookahead`]. dictionaries like [`crate::Automaton`] number
+/// their nodes densely, so these are looked up in a table rather than hashed
+#[derive(Debug, Clone)]
+enum Index {
+    Dense(Vec<u32>),
+    Sparse(HashMap<NodeId, usize>),
+}
+
+impl Index {
+    // not in the table
+    const NONE: u32 = u32::MAX;
+
+    fn new(index: HashMap<NodeId, usize>) -> Self {
+        let max = index.keys().copied().max().unwrap_or(0);
+        // a table at most a few times as large as the map is worth it
+        if max >= 4 * index.len() as NodeId + 1024 || index.len() >= Self::NONE as usize {
+            return Self::Sparse(index);
+        }
+        let mut table = vec![Self::NONE; max as usize + 1];
+        for (node, i) in index {
+            table[node as usize] = i as u32;
+        }
+        Self::Dense(table)
+    }
+
+    fn get(&self, node: NodeId) -> Option<usize> {
+        match self {
+            Self::Dense(table) => table
+                .get(node as usize)
+                .filter(|&&i| i != Self::NONE)
+                .map(|&i| i as usize),
+            Self::Sparse(map) => map.get(&node).copied(),
+        }
+    }
+}
+
+impl Lookahead {
+    /// the deepest lookahead, as the ends of a word are kept in the bits of a `u64`
+    pub const MAX_DEPTH: usize = 63;
+
+    /// a `depth` beyond [`Lookahead::MAX_DEPTH`] is cut to it
+    pub fn new(root: &impl Dictionary, depth: usize) -> Self {
+        let depth = depth.min(Self::MAX_DEPTH);
+
+        let mut nodes = vec![root.root()];
+        let mut index = HashMap::from([(root.root(), 0)]);
+        let mut children = vec![];
+        let mut is_word = vec![];
+        let mut has_children = vec![];
+        let mut i = 0;
+        while i < nodes.len() {
+            let node = nodes[i];
+            let mut node_children = vec![];
+            for b in (root.children(node) & CHARSET).iter() {
+                let child = root.child(node, b).expect("the byte is a child");
+                let next = nodes.len();
+                node_children.push(*index.entry(child).or_insert_with(|| {
+                    nodes.push(child);
+                    next
+                }));
+            }
+            children.push(node_children);
+            is_word.push(root.is_word(node));
+            has_children.push(root.has_children(node));
+            i += 1;
+        }
+        let n = nodes.len();
+        // words after a special char start from all of these, not only the root
+        let start = root
+            .start()
+            .nodes()
+            .iter()
+            .map(|n| index.get(n).copied())
+            .collect::<Vec<_>>();
+
+        let mut word = vec![ByteSet::EMPTY; n * depth];
+        let mut special = vec![ByteSet::EMPTY; depth];
+        for d in 0..depth {
+            if d == 0 {
+                special[0] = separators(root);
+            } else {
+                special[d] = start.iter().fold(special[d - 1], |acc, s| {
+                    acc | s.map_or(CHARSET, |i| word[i * depth + d - 1])
+                });
+            }
+
+            for i in 0..n {
+                word[i * depth + d] = if d == 0 {
+                    root.children(nodes[i]) & CHARSET
+                } else {
+                    children[i].iter().fold(ByteSet::EMPTY, |acc, &c| {
+                        let mut acc = acc;
+                        if has_children[c] {
+                            acc = acc | word[c * depth + d - 1];
+                        }
+                        if is_word[c] {
+                            acc = acc | special[d - 1];
+                        }
+                        acc
+                    })
+                };
+            }
+        }
+
+        // children have to come before their parents. dictionaries may share suffixes between
+        // words, so the bfs order isn't enough
+        let mut order = Vec::with_capacity(n);
+        let mut seen = vec![false; n];
+        let mut stack = vec![(0, false)];
+        while let Some((i, done)) = stack.pop() {
+            if done {
+                order.push(i);
+            } else if !seen[i] {
+                seen[i] = true;
+                stack.push((i, true));
+                stack.extend(children[i].iter().map(|&c| (c, false)));
+            }
+        }
+
+        let mut min_end = vec![u32::MAX; n];
+        let mut ends = vec![0u64; n];
+        let relevant = u64::MAX >> (Self::MAX_DEPTH - depth);
+        for i in order {
+            ends[i] = children[i].iter().fold(0, |acc, &c| {
+                let mut acc = acc;
+                if is_word[c] {
+                    acc |= !0 << 1;
+                }
+                if has_children[c] {
+                    acc |= ends[c] << 1;
+                }
+                acc & relevant
+            });
+            min_end[i] = children[i]
+                .iter()
+                .map(|&c| {
+                    if is_word[c] {
+                        1
+                    } else {
+                        min_end[c].saturating_add(1)
+                    }
+                })
+                .min()
+                .unwrap_or(u32::MAX);
+        }
+
+        Self {
+            depth,
+            index: Index::new(index),
+            word,
+            special,
+            min_end,
+            ends,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    fn reach(&self, side: &Side, d: usize) -> ByteSet {
+        match side.expected {
+            ExpectedNext::Word => side.queries.nodes().iter().fold(ByteSet::EMPTY, |acc, n| {
+                acc | self
+                    .index
+                    .get(*n)
+                    .map_or(CHARSET, |i| self.word[i * self.depth + d])
+            }),
+            ExpectedNext::Special | ExpectedNext::AfterWord => self.special[d],
+        }
+    }
+
+    fn can_end_after(&self, side: &Side, d: usize) -> bool {
+        match side.expected {
+            ExpectedNext::Word => side.queries.nodes().iter().any(|n| {
+                self.index
+                    .get(*n)
+                    .is_none_or(|i| self.ends[i] & (1 << d) != 0)
+            }),
+            ExpectedNext::Special | ExpectedNext::AfterWord => true,
+        }
+    }
+
+    fn min_end(&self, side: &Side) -> u32 {
+        match side.expected {
+            ExpectedNext::Word => side
+                .queries
+                .nodes()
+                .iter()
+                .map(|n| self.index.get(*n).map_or(0, |i| self.min_end[i]))
+                .min()
+                .unwrap_or(u32::MAX),
+            ExpectedNext::Special | ExpectedNext::AfterWord => 0,
+        }
+    }
+
+    /// whether the two sides may still produce plain texts for the remaining `cipher`. unless
+    /// `whole_words` is set, the texts may end in the middle of a word
+    pub(crate) fn feasible(
+        &self,
+        side1: &Side,
+        side2: &Side,
+        cipher: &[u8],
+        whole_words: bool,
+    ) -> bool {
+        let remaining = u32::try_from(cipher.len()).unwrap_or(u32::MAX);
+        if whole_words && (self.min_end(side1) > remaining || self.min_end(side2) > remaining) {
+            return false;
+        }
+        if whole_words
+            && cipher.len() <= self.depth
+            && !(self.can_end_after(side1, cipher.len()) && self.can_end_after(side2, cipher.len()))
+        {
+            return false;
+        }
+
+        cipher
+            .iter()
+            .take(self.depth)
+            .enumerate()
+            .all(|(d, &c)| !(self.reach(side1, d) & self.reach(side2, d).xor(c)).is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dictionary::WordSet, xor_strings, Automaton, Queries};
+
+    fn check(root: &impl Dictionary) {
+        let lookahead = Lookahead::new(root, 3);
+        let start = Side::new(Queries::new(root.root()), ExpectedNext::Word);
+
+        assert!(lookahead.feasible(&start, &start, &xor_strings("yes", "the"), true));
+        // neither "ye" nor "th" can be finished in time, which only matters for whole words
+        assert!(!lookahead.feasible(&start, &start, &xor_strings("ye", "th"), true));
+        assert!(lookahead.feasible(&start, &start, &xor_strings("ye", "th"), false));
+
+        let y = Side::new(Queries::new(root.walk("y").unwrap()), ExpectedNext::Word);
+        let t = Side::new(Queries::new(root.walk("t").unwrap()), ExpectedNext::Word);
+        assert!(lookahead.feasible(&y, &t, &xor_strings("es", "he"), true));
+        assert!(!lookahead.feasible(&y, &t, &xor_strings("es", "ha"), false));
+
+        assert_eq!(Lookahead::new(root, 100).depth(), Lookahead::MAX_DEPTH);
+    }
+
+    #[test]
+    fn rejects_doomed_states() {
+        let words = ["yes", "the", "cats"];
+        let automaton = words
+            .map(|w| (w, ()))
+            .into_iter()
+            .collect::<Automaton<()>>();
+        check(&automaton);
+        check(&WordSet::new(words));
+
+        // the automaton's nodes are numbered densely, those of the word set aren't
+        let dense = Lookahead::new(&automaton, 3).index;
+        assert!(matches!(dense, Index::Dense(_)));
+        let sparse = Lookahead::new(&WordSet::new(words), 3).index;
+        assert!(matches!(sparse, Index::Sparse(_)));
+    }
+}
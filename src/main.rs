@@ -1,32 +1,135 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
 use xoracle::{
-    all::build_trie_importance, build_trie, crack, crack_non_rec, xor, xor_strings, Queries,
+    charset,
+    checkpoint::{Checkpoint, Checkpointing, Run},
+    compiled::CompiledDict,
+    compose::DictConfig,
+    compound::Compounds,
+    corpus::{write_word_list, Corpus},
+    count::Segmentations,
+    crack,
+    dp::viterbi,
+    marginals::{confidence, highlight, marginals_of},
+    special,
+    transducer::FstDict,
+    unknown::{CharModel, UnknownConfig, UnknownWords},
+    xor, xor_strings, Automaton, Dictionary, Product, Solver, SolverConfig, Strategy, WordValue,
 };
 
+const MAX_LISTED: u128 = 10_000;
+const PAGE: usize = 20;
+const MAX_STATES: usize = 1_000_000;
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// reports a problem with the arguments or inputs and exits
+fn fail(err: impl std::fmt::Display) -> ! {
+    eprintln!("error: {err}");
+    std::process::exit(1)
+}
+
+/// the dictionary pipeline of `XORACLE_DICT_CONFIG`, a json file, if it is set
+fn custom_dict_config() -> Option<DictConfig> {
+    std::env::var_os("XORACLE_DICT_CONFIG").map(|path| {
+        DictConfig::load(Path::new(&path))
+            .unwrap_or_else(|e| fail(format!("failed to load the dictionary config: {e}")))
+    })
+}
+
+/// the dictionary pipeline of `XORACLE_DICT_CONFIG`, or the default one
+fn dict_config() -> DictConfig {
+    custom_dict_config().unwrap_or_default()
+}
+
+/// `xoracle build-dict <out> [word list]` compiles a word list, or the words of the dictionary
+/// pipeline, for `XORACLE_DICT`. an `out` ending in `.fst` makes a transducer, which is a lot
+/// smaller for large lists
+fn build_dict() {
+    let out = std::env::args()
+        .nth(2)
+        .unwrap_or_else(|| fail("usage: xoracle build-dict <out> [word list]"));
+    let config = match std::env::args().nth(3) {
+        Some(path) => DictConfig::with_list(path.into()),
+        None => dict_config(),
+    };
+    let words = config
+        .scored_words()
+        .unwrap_or_else(|e| fail(format!("failed to read the word lists: {e}")));
+    let out = PathBuf::from(out);
+    if out.extension().is_some_and(|ext| ext == "fst") {
+        // like the compiled dictionary, only words the searches can spell
+        let dict = FstDict::new(
+            words
+                .into_iter()
+                .filter(|(word, _)| word.bytes().all(|b| charset().contains(&b))),
+        );
+        dict.save(&out)
+            .unwrap_or_else(|e| fail(format!("failed to write the dictionary: {e}")));
+        println!("wrote {} words to {}", dict.len(), out.display());
+        return;
+    }
+    let dict = CompiledDict::new(words);
+    dict.save(&out)
+        .unwrap_or_else(|e| fail(format!("failed to write the dictionary: {e}")));
+    println!(
+        "wrote {} nodes to {}",
+        dict.automaton.node_count(),
+        out.display()
+    );
+}
+
+/// `xoracle corpus <out> <min count> <max words> <file>...` counts the words of text files into a
+/// word list for `build-dict`. a maximum of `0` words keeps all of them
+fn count_corpus() {
+    let usage = "usage: xoracle corpus <out> <min count> <max words> <file>...";
+    let args = std::env::args().skip(2).collect::<Vec<_>>();
+    let [out, min_count, max_words, files @ ..] = args.as_slice() else {
+        fail(usage);
+    };
+    let min_count = min_count.parse().unwrap_or_else(|_| fail(usage));
+    let max_words = match max_words.parse().unwrap_or_else(|_| fail(usage)) {
+        0 => None,
+        n => Some(n),
+    };
+
+    let mut corpus = Corpus::default();
+    for file in files {
+        corpus
+            .add_file(Path::new(file))
+            .unwrap_or_else(|e| fail(format!("failed to read {file}: {e}")));
+    }
+    let words = corpus.words(min_count, max_words);
+    let list =
+        File::create(out).unwrap_or_else(|e| fail(format!("failed to create the word list: {e}")));
+    write_word_list(words.iter().copied(), BufWriter::new(list))
+        .unwrap_or_else(|e| fail(format!("failed to write the word list: {e}")));
+    println!("wrote {} of {} words to {out}", words.len(), corpus.len());
+}
+
 fn main() {
+    match std::env::args().nth(1).as_deref() {
+        Some("build-dict") => return build_dict(),
+        Some("corpus") => return count_corpus(),
+        _ => {}
+    }
+
     let a = std::env::args().nth(1).unwrap_or("yes".to_owned());
     let b = std::env::args().nth(2).unwrap_or("the".to_owned());
-
-    let words = include_str!("./en_50k.txt");
-
-    let wordsi = words
-        .lines()
-        .filter_map(|x| x.split_whitespace().next())
-        .filter(|x| x.is_ascii())
-        .filter(|x| x.len() > 1 || *x == "a")
-        .filter(|x| *x != "th")
-        .filter(|x| *x != "ye");
-
-    let trie = build_trie(wordsi.clone());
-    let special_chars = "'\" ,.";
-    let more_trie = build_trie(
-        wordsi.clone(), // .flat_map(|x| special_chars.chars().map(move |ch| format!("{ch}{x}"))), // .chain(
-                        //     words
-                        //         .lines()
-                        //         .filter_map(|x| x.split_whitespace().next())
-                        //         .filter(|x| x.is_ascii())
-                        //         .flat_map(|x| special_chars.chars().map(move |ch| format!("{x}{ch}"))),
-                        // )
-    );
+    let strategy: Option<Strategy> = std::env::args()
+        .nth(3)
+        .map(|s| s.parse().unwrap_or_else(|e| fail(e)));
+    // searches with a checkpoint file continue from it if it exists, and save to it every minute
+    // and on ctrl-c
+    let checkpoint = std::env::args().nth(4).map(PathBuf::from);
 
     let cipher = xor_strings(&a, &b);
     println!("cipher: {cipher:02x?}");
@@ -34,40 +137,219 @@ fn main() {
     println!("    {a:?}");
     println!("    {b:?}");
 
-    let res = crack(
-        &cipher,
-        &more_trie,
-        Queries::new(trie.inc_search()),
-        Queries::new(trie.inc_search()),
-    );
+    let custom_config = custom_dict_config();
+    // without a config, the scored dictionary only drops words that aren't ascii, as it always
+    // did. a config is used for both dictionaries
+    let scored_config = custom_config
+        .clone()
+        .unwrap_or_else(DictConfig::ascii_words);
+    let config = custom_config.unwrap_or_default();
+    // the letters of unknown words are modelled on the word lists of the config, also when the
+    // dictionary itself comes from `XORACLE_DICT`
+    let unknown = config.unknown.map(|unknown| {
+        let words = config.words().expect("failed to read the word lists");
+        let letters = charset()
+            .iter()
+            .copied()
+            .filter(|c| !special().contains(c))
+            .collect::<Vec<_>>();
+        let model = CharModel::new(&letters, words.iter().map(|(w, c)| (w.as_str(), *c)));
+        (model, unknown)
+    });
+    let search = Search {
+        strategy,
+        checkpoint,
+        unknown,
+        compound_penalty: config.compound_penalty,
+    };
 
-    if let Some((a, b)) = res {
+    // a dictionary made with `build-dict` replaces both built in ones. transducers are mapped into
+    // memory instead of being read
+    match std::env::var_os("XORACLE_DICT").map(PathBuf::from) {
+        Some(path) if path.extension().is_some_and(|ext| ext == "fst") => {
+            let dict = FstDict::open(&path).expect("failed to load the dictionary");
+            run_compounds(&cipher, &dict, &dict, search);
+        }
+        Some(path) => {
+            let dict = CompiledDict::load(&path)
+                .expect("failed to load the dictionary")
+                .automaton;
+            run_compounds(&cipher, &dict, &dict, search);
+        }
+        None => {
+            let read = |config: &DictConfig| {
+                config
+                    .scored_words()
+                    .expect("failed to read the word lists")
+            };
+            let more_trie: Automaton<f64> =
+                read(&config).iter().map(|(word, _)| (word, 0.0)).collect();
+            let trie: Automaton<f64> = read(&scored_config).into_iter().collect();
+            run_compounds(&cipher, &more_trie, &trie, search);
+        }
+    }
+}
+
+/// how the last and most thorough search of `run` goes
+struct Search {
+    /// `None` if it wasn't given, which lists some solutions instead if there are too many
+    strategy: Option<Strategy>,
+    checkpoint: Option<PathBuf>,
+    unknown: Option<(CharModel, UnknownConfig)>,
+    compound_penalty: Option<f64>,
+}
+
+/// `run`, with hyphenated compounds if the config allows them
+fn run_compounds(
+    cipher: &[u8],
+    more_trie: &impl Dictionary<Value = f64>,
+    trie: &impl Dictionary<Value = f64>,
+    search: Search,
+) {
+    match search.compound_penalty {
+        Some(penalty) => run(
+            cipher,
+            &Compounds::new(more_trie, penalty),
+            &Compounds::new(trie, penalty),
+            search,
+        ),
+        None => run(cipher, more_trie, trie, search),
+    }
+}
+
+/// finds some plain text with `more_trie`, then looks at the solutions scored by `trie`
+fn run(
+    cipher: &[u8],
+    more_trie: &impl Dictionary<Value: WordValue>,
+    trie: &impl Dictionary<Value = f64>,
+    search: Search,
+) {
+    let res = crack(cipher, more_trie, more_trie.start(), more_trie.start());
+
+    if let Some(sol) = res {
         println!("found valid plain text");
-        println!("  {:?}", std::str::from_utf8(&a).unwrap());
-        println!("  {:?}", std::str::from_utf8(&b).unwrap());
+        println!("  {:?}", sol.left.text);
+        println!("  {:?}", sol.right.text);
 
-        let cipher = xor(a, b);
+        let cipher = xor(sol.left.text.bytes(), sol.right.text.bytes());
         println!("cipher: {cipher:02x?}");
     } else {
         println!("couldn't find valid plain text");
     }
 
-    let trie = build_trie_importance(
-        words
-            .lines()
-            .filter_map(|x| x.split_once(' '))
-            .filter_map(|(word, p)| p.parse().ok().map(|p| (word, p)))
-            .filter(|(x, _)| x.is_ascii()),
-    );
+    // the product can't be interrupted, so a search that is saved on ctrl-c goes right to it
+    if search.checkpoint.is_none() {
+        let product = Product::new(cipher, trie);
+        if let Some(best) = viterbi(&product) {
+            let (a, b) = (&best.left.text, &best.right.text);
+            println!("best plain text (score {})", best.score);
+            match marginals_of(&product) {
+                Some(m) => {
+                    println!("  {}", highlight(a, &m.left, 0.9));
+                    println!("  {}", confidence(a, &m.left));
+                    println!("  {}", highlight(b, &m.right, 0.9));
+                    println!("  {}", confidence(b, &m.right));
+                }
+                None => {
+                    println!("  {:?}", a);
+                    println!("  {:?}", b);
+                }
+            }
+        }
 
-    let ans = crack_non_rec(&cipher, &trie);
+        let solutions = Segmentations::of(product);
+        println!("{} valid segmentations", solutions.count());
+        if solutions.count() > MAX_LISTED && search.strategy.is_none() {
+            println!("too many to search them all, the first {PAGE}:");
+            for sol in solutions.page(0, PAGE) {
+                println!("  {:?}", sol.left.text);
+                println!("  {:?}", sol.right.text);
+                println!();
+            }
+            return;
+        }
+    }
+
+    // unknown words are only searched for here, the products above would grow far too large
+    let strategy = search.strategy.unwrap_or_default();
+    match search.unknown {
+        Some((model, config)) => {
+            let unknown = UnknownWords::new(trie, model, config);
+            solve(cipher, &unknown, strategy, search.checkpoint);
+        }
+        None => solve(cipher, trie, strategy, search.checkpoint),
+    }
+}
+
+fn solve(
+    cipher: &[u8],
+    trie: &impl Dictionary<Value = f64>,
+    strategy: Strategy,
+    checkpoint: Option<PathBuf>,
+) {
+    let config = SolverConfig {
+        max_states: Some(MAX_STATES),
+        ..Default::default()
+    };
+    let outcome = match checkpoint {
+        Some(path) => {
+            let stop = Arc::new(AtomicBool::new(false));
+            let handler = stop.clone();
+            ctrlc::set_handler(move || handler.store(true, Ordering::Relaxed))
+                .expect("failed to set the ctrl-c handler");
+
+            let start = if path.exists() {
+                let start = Checkpoint::load(&path)
+                    .unwrap_or_else(|e| fail(format!("failed to load the checkpoint: {e}")));
+                if start.cipher != cipher {
+                    fail("the checkpoint is for another cipher");
+                }
+                eprintln!(
+                    "resuming after {} states with {} solutions",
+                    start.stats().expanded,
+                    start.solutions().len()
+                );
+                start
+            } else {
+                Checkpoint::new(cipher, trie, strategy, config).unwrap_or_else(|e| fail(e))
+            };
+            let checkpointing = Checkpointing {
+                path: path.clone(),
+                interval: Some(CHECKPOINT_INTERVAL),
+                stop,
+            };
+            match start.run(trie, &checkpointing) {
+                Ok(Run::Finished(outcome)) => outcome,
+                Ok(Run::Stopped(stopped)) => {
+                    println!(
+                        "stopped with {} solutions so far, saved to {}",
+                        stopped.solutions().len(),
+                        path.display()
+                    );
+                    return;
+                }
+                Err(e) => fail(format!("failed to checkpoint the search: {e}")),
+            }
+        }
+        None => strategy.solve(cipher, trie, &config),
+    };
+    eprintln!(
+        "expanded {} states, dropped {}{}",
+        outcome.stats.expanded,
+        outcome.stats.dropped,
+        if outcome.stats.exhaustive {
+            ""
+        } else {
+            ", the search was not exhaustive"
+        }
+    );
 
-    if ans.is_empty() {
+    if outcome.solutions.is_empty() {
         println!("couldn't find valid plain text");
     } else {
-        for (a, b) in ans {
-            println!("  {:?}", a);
-            println!("  {:?}", b);
+        for sol in outcome.solutions {
+            println!("  {:?}", sol.left.text);
+            println!("  {:?}", sol.right.text);
             println!();
         }
     }
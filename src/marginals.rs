@@ -0,0 +1,156 @@
+use std::f64::consts::LN_2;
+
+use crate::{
+    product::{log_add, Product},
+    Dictionary,
+};
+
+/// the candidate chars for one byte of a plain text, most likely first
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Distribution(pub Vec<(u8, f64)>);
+
+impl Distribution {
+    pub fn top(&self) -> Option<(u8, f64)> {
+        self.0.first().copied()
+    }
+
+    pub fn probability(&self, chr: u8) -> f64 {
+        self.0
+            .iter()
+            .find(|(ch, _)| *ch == chr)
+            .map_or(0.0, |(_, p)| *p)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Marginals {
+    pub left: Vec<Distribution>,
+    pub right: Vec<Distribution>,
+}
+
+/// runs the forward-backward algorithm over the same states as [`crate::crack_non_rec`]. every
+/// pair of plain texts is weighted by `2^score`, so the result is the probability of every char
+/// at every position, given that the plain texts consist of dictionary words
+pub fn marginals(cipher: &[u8], root: &impl Dictionary<Value = f64>) -> Option<Marginals> {
+    marginals_of(&Product::new(cipher, root))
+}
+
+/// [`marginals`] over a product that was already built
+pub fn marginals_of<D: Dictionary<Value = f64>>(product: &Product<D>) -> Option<Marginals> {
+    let n = product.cipher.len();
+    let weight = |score: f64| score * LN_2;
+
+    let mut forward = vec![vec![0.0]];
+    for i in 0..n {
+        let mut next = vec![f64::NEG_INFINITY; product.layers[i + 1].len()];
+        for (from, &alpha) in forward[i].iter().enumerate() {
+            for step in product.steps(i, from) {
+                next[step.to] = log_add(next[step.to], alpha + weight(step.score()));
+            }
+        }
+        forward.push(next);
+    }
+
+    let mut backward: Vec<f64> = product
+        .last()
+        .pairs
+        .iter()
+        .map(|pair| {
+            if product.is_final(pair) {
+                0.0
+            } else {
+                f64::NEG_INFINITY
+            }
+        })
+        .collect();
+    let total = forward[n]
+        .iter()
+        .zip(&backward)
+        .fold(f64::NEG_INFINITY, |acc, (a, b)| log_add(acc, a + b));
+    if total == f64::NEG_INFINITY {
+        return None;
+    }
+
+    let mut left = vec![[f64::NEG_INFINITY; 256]; n];
+    let mut right = vec![[f64::NEG_INFINITY; 256]; n];
+    for i in (0..n).rev() {
+        let mut prev = vec![f64::NEG_INFINITY; product.layers[i].len()];
+        for (from, &alpha) in forward[i].iter().enumerate() {
+            for step in product.steps(i, from) {
+                let rest = weight(step.score()) + backward[step.to];
+                prev[from] = log_add(prev[from], rest);
+
+                let through = alpha + rest;
+                left[i][step.ch1 as usize] = log_add(left[i][step.ch1 as usize], through);
+                right[i][step.ch2 as usize] = log_add(right[i][step.ch2 as usize], through);
+            }
+        }
+        backward = prev;
+    }
+
+    let normalize = |column: &[f64; 256]| {
+        let mut dist = (0..=255u8)
+            .map(|ch| (ch, (column[ch as usize] - total).exp()))
+            .filter(|(_, p)| *p > 0.0)
+            .collect::<Vec<_>>();
+        dist.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Distribution(dist)
+    };
+
+    Some(Marginals {
+        left: left.iter().map(normalize).collect(),
+        right: right.iter().map(normalize).collect(),
+    })
+}
+
+/// draws every byte of `text` whose most likely candidate has a probability below `threshold`
+/// inverted
+pub fn highlight(text: &str, dists: &[Distribution], threshold: f64) -> String {
+    let mut res = String::with_capacity(text.len());
+    for (ch, dist) in text.chars().zip(dists) {
+        if dist.top().is_none_or(|(_, p)| p < threshold) {
+            res.push_str("\x1b[7m");
+            res.push(ch);
+            res.push_str("\x1b[0m");
+        } else {
+            res.push(ch);
+        }
+    }
+    res
+}
+
+/// the probability of every byte of `text`, as a digit from `0` to `9`
+pub fn confidence(text: &str, dists: &[Distribution]) -> String {
+    text.bytes()
+        .zip(dists)
+        .map(|(ch, dist)| {
+            let p = dist.probability(ch);
+            char::from(b'0' + ((p * 10.0) as u8).min(9))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture, xor_strings};
+
+    #[test]
+    fn marginals_sum_to_one() {
+        let root = fixture::dict();
+        let cipher = fixture::cipher();
+
+        let m = marginals(&cipher, &root).expect("there is a solution");
+        for dist in m.left.iter().chain(&m.right) {
+            let sum: f64 = dist.0.iter().map(|(_, p)| p).sum();
+            assert!((sum - 1.0).abs() < 1e-9);
+        }
+
+        // ("the", "yes") and ("yes", "the") weigh 2^5 each, ("kha", "few") and ("few", "kha") 2^2
+        let p = 32.0 / (2.0 * 32.0 + 2.0 * 4.0);
+        assert!((m.left[0].probability(b't') - p).abs() < 1e-9);
+        assert_eq!(confidence("the", &m.left), "444");
+
+        assert_eq!(marginals(&xor_strings("yes", "thf"), &root), None);
+    }
+}
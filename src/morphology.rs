@@ -0,0 +1,198 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+/// makes inflected forms like "encrypted" from the stems of a word list, which frequency lists
+/// often lack. the counts of the forms are derived from those of the stems, so they are scored
+/// like any other word, but lower than their stems, as the rules also make forms that aren't
+/// words. doubled consonants as in "stopped" aren't made
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Morphology {
+    pub inflections: Vec<Inflection>,
+    /// shorter words aren't inflected
+    pub min_stem_len: usize,
+    /// words that aren't inflected, like "the" or "and" whose forms would be junk
+    pub skip: BTreeSet<String>,
+}
+
+/// one kind of form, e.g. the plural. the first rule that fits the stem makes it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Inflection {
+    pub rules: Vec<InflectionRule>,
+    /// the count of a form is the count of its stem times this
+    #[serde(default = "quarter")]
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InflectionRule {
+    /// the stem has to end with one of these, any stem fits if there are none
+    #[serde(default)]
+    pub after: Vec<String>,
+    /// removed from the end of the stem, e.g. the "y" of "carries"
+    #[serde(default)]
+    pub strip: String,
+    pub suffix: String,
+}
+
+fn quarter() -> f64 {
+    0.25
+}
+
+// the most common words of at least three letters that have no forms of their own
+const FUNCTION_WORDS: &[&str] = &[
+    "about", "above", "after", "again", "against", "all", "also", "and", "any", "are", "because",
+    "been", "before", "both", "but", "can", "could", "did", "does", "each", "for", "from", "had",
+    "has", "have", "her", "here", "hers", "him", "his", "how", "into", "its", "may", "might",
+    "more", "most", "much", "must", "nor", "not", "now", "off", "once", "only", "our", "ours",
+    "out", "over", "own", "same", "she", "should", "some", "such", "than", "that", "the", "their",
+    "them", "then", "there", "these", "they", "this", "those", "through", "too", "under", "until",
+    "very", "was", "were", "what", "when", "where", "which", "while", "who", "whom", "why", "will",
+    "with", "would", "yet", "you", "your", "yours",
+];
+
+impl InflectionRule {
+    fn new(after: &[&str], strip: &str, suffix: &str) -> Self {
+        Self {
+            after: after.iter().map(|&s| s.to_owned()).collect(),
+            strip: strip.to_owned(),
+            suffix: suffix.to_owned(),
+        }
+    }
+
+    fn apply(&self, stem: &str) -> Option<String> {
+        let fits = self.after.is_empty() || self.after.iter().any(|end| stem.ends_with(end));
+        let rest = stem.strip_suffix(self.strip.as_str())?;
+        fits.then(|| format!("{rest}{}", self.suffix))
+    }
+}
+
+impl Inflection {
+    fn new(rules: Vec<InflectionRule>) -> Self {
+        Self {
+            rules,
+            weight: quarter(),
+        }
+    }
+
+    pub fn apply(&self, stem: &str) -> Option<String> {
+        self.rules.iter().find_map(|rule| rule.apply(stem))
+    }
+}
+
+impl Default for Morphology {
+    /// english plurals and -ed, -ing and -ly forms, skipping the function words
+    fn default() -> Self {
+        let consonant_y = "bcdfghjklmnpqrstvwxz"
+            .chars()
+            .map(|c| format!("{c}y"))
+            .collect::<Vec<_>>();
+        let consonant_y = consonant_y.iter().map(String::as_str).collect::<Vec<_>>();
+        let rule = InflectionRule::new;
+        Self {
+            inflections: vec![
+                Inflection::new(vec![
+                    rule(&["s", "x", "z", "ch", "sh"], "", "es"),
+                    rule(&consonant_y, "y", "ies"),
+                    rule(&[], "", "s"),
+                ]),
+                Inflection::new(vec![
+                    rule(&["e"], "", "d"),
+                    rule(&consonant_y, "y", "ied"),
+                    rule(&[], "", "ed"),
+                ]),
+                Inflection::new(vec![
+                    rule(&["ee", "ye", "oe"], "", "ing"),
+                    rule(&["ie"], "ie", "ying"),
+                    rule(&["e"], "e", "ing"),
+                    rule(&[], "", "ing"),
+                ]),
+                Inflection::new(vec![
+                    rule(&["le"], "e", "y"),
+                    rule(&consonant_y, "y", "ily"),
+                    rule(&["ic"], "", "ally"),
+                    rule(&[], "", "ly"),
+                ]),
+            ],
+            min_stem_len: 3,
+            skip: FUNCTION_WORDS.iter().map(|&w| w.to_owned()).collect(),
+        }
+    }
+}
+
+impl Morphology {
+    /// `words` with the forms of its words added. words that are in the list already keep their
+    /// count, a form made from several stems gets the highest count
+    pub fn apply(&self, words: Vec<(String, u64)>) -> Vec<(String, u64)> {
+        let mut forms = BTreeMap::<String, u64>::new();
+        let stems = words
+            .iter()
+            .filter(|(w, _)| w.len() >= self.min_stem_len && !self.skip.contains(w));
+        for (stem, count) in stems {
+            for inflection in &self.inflections {
+                if let Some(form) = inflection.apply(stem) {
+                    let count = (*count as f64 * inflection.weight).round().max(1.0) as u64;
+                    let entry = forms.entry(form).or_default();
+                    *entry = (*entry).max(count);
+                }
+            }
+        }
+        forms.extend(words);
+        forms.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{all::build_trie_inflected, Dictionary};
+
+    #[test]
+    fn inflects_stems() {
+        let words = [
+            ("encrypt", 100),
+            ("carry", 10),
+            ("box", 8),
+            ("make", 20),
+            ("die", 6),
+            ("simple", 4),
+            ("basic", 4),
+            ("as", 50),
+            ("boxes", 1),
+            ("the", 1000),
+            ("and", 800),
+        ];
+        let forms = Morphology::default().apply(words.map(|(w, c)| (w.to_owned(), c)).to_vec());
+        let count = |word: &str| forms.iter().find(|(w, _)| w == word).map(|(_, c)| *c);
+        assert_eq!(count("encrypted"), Some(25));
+        assert_eq!(count("encrypting"), Some(25));
+        assert_eq!(count("carries"), Some(3));
+        assert_eq!(count("carried"), Some(3));
+        assert_eq!(count("boxes"), Some(1));
+        assert_eq!(count("making"), Some(5));
+        assert_eq!(count("made"), None);
+        assert_eq!(count("dying"), Some(2));
+        assert_eq!(count("simply"), Some(1));
+        assert_eq!(count("basically"), Some(1));
+        assert_eq!(count("ass"), None);
+        assert_eq!(count("thes"), None);
+        assert_eq!(count("anded"), None);
+
+        let config: Morphology = serde_json::from_str(
+            r#"{ "inflections": [{ "rules": [{ "suffix": "ness" }], "weight": 0.1 }] }"#,
+        )
+        .unwrap();
+        assert_eq!(config.min_stem_len, 3);
+        assert!(config.skip.contains("the"));
+        assert_eq!(
+            config.apply(vec![("kind".into(), 30)])[1],
+            ("kindness".into(), 3)
+        );
+
+        let trie = build_trie_inflected(words.into_iter(), &Morphology::default());
+        assert_eq!(trie.get("encrypted"), Some(25f64.log2() + 18.0));
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use crate::{
+    expand::{alphabet, PairMasks, Side},
+    Dictionary, ExpectedNext, PlainText, Solution,
+};
+
+/// `ln(e^a + e^b)`
+pub(crate) fn log_add(a: f64, b: f64) -> f64 {
+    if a == f64::NEG_INFINITY {
+        return b;
+    }
+    if b == f64::NEG_INFINITY {
+        return a;
+    }
+    let (hi, lo) = if a > b { (a, b) } else { (b, a) };
+    hi + (lo - hi).exp().ln_1p()
+}
+
+/// a state of the product automaton, i.e. the position of both plain texts in the dictionary
+pub(crate) type Pair = (Side, Side);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Step {
+    pub ch1: u8,
+    pub ch2: u8,
+    /// value of the word finished on the left by `ch1`
+    pub left: Option<f64>,
+    /// value of the word finished on the right by `ch2`
+    pub right: Option<f64>,
+    /// index of the pair in the next layer
+    pub to: usize,
+}
+
+impl Step {
+    pub fn score(&self) -> f64 {
+        self.left.unwrap_or(0.0) + self.right.unwrap_or(0.0)
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Layer {
+    pub pairs: Vec<Pair>,
+    index: HashMap<Pair, usize>,
+}
+
+impl Layer {
+    fn insert(&mut self, pair: Pair) -> usize {
+        *self.index.entry(pair).or_insert_with(|| {
+            self.pairs.push(pair);
+            self.pairs.len() - 1
+        })
+    }
+
+    pub fn get(&self, pair: &Pair) -> Option<usize> {
+        self.index.get(pair).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+}
+
+/// all pairs reachable while reading the cipher, layer `i` holding the pairs after `i` bytes.
+/// building it is the expensive part of [`crate::dp`], [`crate::marginals`] and
+/// [`crate::count`], so it can be built once and passed to each of them
+pub struct Product<'a, D> {
+    pub(crate) root: &'a D,
+    pub(crate) cipher: &'a [u8],
+    masks: PairMasks,
+    pub(crate) layers: Vec<Layer>,
+}
+
+impl<'a, D: Dictionary<Value = f64>> Product<'a, D> {
+    pub fn new(cipher: &'a [u8], root: &'a D) -> Self {
+        let start = Side::new(root.start(), ExpectedNext::Word);
+        let mut first = Layer::default();
+        first.insert((start, start));
+
+        let mut product = Self {
+            root,
+            cipher,
+            masks: PairMasks::new(alphabet(root)),
+            layers: vec![first],
+        };
+
+        for i in 0..cipher.len() {
+            let mut next = Layer::default();
+            for &pair in &product.layers[i].pairs {
+                product.for_each_successor(i, pair, |_, _, _, _, pair| {
+                    next.insert(pair);
+                });
+            }
+            product.layers.push(next);
+        }
+
+        product
+    }
+
+    fn for_each_successor(
+        &self,
+        i: usize,
+        (side1, side2): Pair,
+        mut f: impl FnMut(u8, u8, Option<f64>, Option<f64>, Pair),
+    ) {
+        let c = self.cipher[i];
+        let (allowed1, allowed2) = (side1.allowed(self.root), side2.allowed(self.root));
+        for (ch1, ch2) in self.masks.pairs(c, allowed1, allowed2) {
+            for (next1, left) in side1.next(self.root, ch1) {
+                for (next2, right) in side2.next(self.root, ch2) {
+                    f(ch1, ch2, left, right, (next1, next2));
+                }
+            }
+        }
+    }
+
+    /// the transitions from the pair `from` of layer `i` into layer `i + 1`
+    pub(crate) fn steps(&self, i: usize, from: usize) -> Vec<Step> {
+        let mut steps = vec![];
+        self.for_each_successor(
+            i,
+            self.layers[i].pairs[from],
+            |ch1, ch2, left, right, pair| {
+                let to = self.layers[i + 1]
+                    .get(&pair)
+                    .expect("successors are part of the next layer");
+                steps.push(Step {
+                    ch1,
+                    ch2,
+                    left,
+                    right,
+                    to,
+                });
+            },
+        );
+        steps
+    }
+
+    /// whether both plain texts may end in this pair
+    pub(crate) fn is_final(&self, (side1, side2): &Pair) -> bool {
+        side1.is_final() && side2.is_final()
+    }
+
+    /// the solution made of `steps`, the `i`-th step going from layer `i` to `i + 1`
+    pub(crate) fn solution(&self, steps: &[Step]) -> Solution {
+        let after = |i: usize, step: &Step| self.layers[i + 1].pairs[step.to];
+        Solution::new(
+            PlainText::from_events(
+                steps
+                    .iter()
+                    .enumerate()
+                    .map(|(i, step)| (step.ch1, after(i, step).0.expected, step.left)),
+            ),
+            PlainText::from_events(
+                steps
+                    .iter()
+                    .enumerate()
+                    .map(|(i, step)| (step.ch2, after(i, step).1.expected, step.right)),
+            ),
+        )
+    }
+
+    pub(crate) fn last(&self) -> &Layer {
+        self.layers.last().expect("there is always a first layer")
+    }
+}
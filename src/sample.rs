@@ -0,0 +1,170 @@
+use std::f64::consts::LN_2;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    product::{log_add, Product},
+    Dictionary, Solution,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Weighting {
+    /// every pair of segmentations is equally likely
+    Uniform,
+    /// a pair is drawn with probability proportional to `2^(score / temperature)`
+    Score { temperature: f64 },
+}
+
+impl Default for Weighting {
+    fn default() -> Self {
+        Weighting::Score { temperature: 1.0 }
+    }
+}
+
+/// draws random solutions from the same states as [`crate::crack_non_rec`]
+pub struct Sampler<'a, D> {
+    product: Product<'a, D>,
+    weighting: Weighting,
+    // `backward[i][pair]` is the log of the total weight of all ways to finish from `pair`
+    backward: Vec<Vec<f64>>,
+}
+
+impl<'a, D: Dictionary<Value = f64>> Sampler<'a, D> {
+    pub fn new(cipher: &'a [u8], root: &'a D, weighting: Weighting) -> Self {
+        let product = Product::new(cipher, root);
+        let n = cipher.len();
+
+        let mut sampler = Self {
+            product,
+            weighting,
+            backward: vec![vec![]; n + 1],
+        };
+
+        sampler.backward[n] = sampler
+            .product
+            .last()
+            .pairs
+            .iter()
+            .map(|pair| {
+                if sampler.product.is_final(pair) {
+                    0.0
+                } else {
+                    f64::NEG_INFINITY
+                }
+            })
+            .collect();
+        for i in (0..n).rev() {
+            sampler.backward[i] = (0..sampler.product.layers[i].len())
+                .map(|from| {
+                    sampler
+                        .product
+                        .steps(i, from)
+                        .iter()
+                        .fold(f64::NEG_INFINITY, |acc, step| {
+                            log_add(
+                                acc,
+                                sampler.weight(step.score()) + sampler.backward[i + 1][step.to],
+                            )
+                        })
+                })
+                .collect();
+        }
+
+        sampler
+    }
+
+    fn weight(&self, score: f64) -> f64 {
+        match self.weighting {
+            Weighting::Uniform => 0.0,
+            Weighting::Score { temperature } => score * LN_2 / temperature,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.backward[0][0] == f64::NEG_INFINITY
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> Option<Solution> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let n = self.product.cipher.len();
+        let mut path = Vec::with_capacity(n);
+        let mut at = 0;
+        for i in 0..n {
+            let steps = self.product.steps(i, at);
+            let total = self.backward[i][at];
+            let mut x: f64 = rng.gen();
+            let mut chosen = None;
+            for step in &steps {
+                let p = (self.weight(step.score()) + self.backward[i + 1][step.to] - total).exp();
+                if p == 0.0 {
+                    continue;
+                }
+                chosen = Some(step);
+                if x < p {
+                    break;
+                }
+                x -= p;
+            }
+            // rounding errors may leave a tiny rest of `x`, the last possible step takes it
+            let step = chosen.expect("a pair with finite weight has a successor");
+            at = step.to;
+            path.push(*step);
+        }
+
+        Some(self.product.solution(&path))
+    }
+}
+
+/// draws `count` solutions using a rng seeded with `seed`
+pub fn sample(
+    cipher: &[u8],
+    root: &impl Dictionary<Value = f64>,
+    weighting: Weighting,
+    count: usize,
+    seed: u64,
+) -> Vec<Solution> {
+    let sampler = Sampler::new(cipher, root, weighting);
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count).map_while(|_| sampler.sample(&mut rng)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{xor_strings, Automaton};
+
+    #[test]
+    fn samples_are_solutions() {
+        let root: Automaton<f64> = [("the", 20.0), ("yes", 20.0), ("kha", 1.0), ("few", 1.0)]
+            .into_iter()
+            .collect();
+        let cipher = xor_strings("yes", "the");
+
+        let texts = |solutions: Vec<Solution>| {
+            solutions
+                .into_iter()
+                .map(|s| (s.left.text, s.right.text))
+                .collect::<Vec<_>>()
+        };
+
+        let uniform = texts(sample(&cipher, &root, Weighting::Uniform, 200, 7));
+        assert_eq!(uniform.len(), 200);
+        assert_eq!(
+            uniform,
+            texts(sample(&cipher, &root, Weighting::Uniform, 200, 7))
+        );
+        assert!(uniform.contains(&("kha".into(), "few".into())));
+
+        // ("yes", "the") is 2^38 times as likely as ("kha", "few")
+        let weighted = texts(sample(&cipher, &root, Weighting::default(), 200, 7));
+        assert!(weighted
+            .iter()
+            .all(|(a, b)| [a.as_str(), b.as_str()] == ["yes", "the"]
+                || [a.as_str(), b.as_str()] == ["the", "yes"]));
+
+        assert!(sample(&xor_strings("yes", "thf"), &root, Weighting::Uniform, 3, 7).is_empty());
+    }
+}
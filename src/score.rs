@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+
+/// what a word list says about a word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WordStats {
+    pub count: u64,
+    /// the position of the word when the list is sorted by count, starting at 1
+    pub rank: usize,
+    /// the sum of the counts of all words of the list
+    pub total: u64,
+}
+
+/// turns the counts of a word list into the values of a dictionary. the searches add up the
+/// values of the words of a solution and prefer the highest sum
+pub trait WordScorer {
+    fn score(&self, word: &str, stats: WordStats) -> f64;
+}
+
+impl<F: Fn(&str, WordStats) -> f64> WordScorer for F {
+    fn score(&self, word: &str, stats: WordStats) -> f64 {
+        self(word, stats)
+    }
+}
+
+/// the built in scorers, configurable from json, e.g. `{ "kind": "zipf-rank", "exponent": 1 }`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Scoring {
+    /// `log2(count) + length_bonus * len`
+    LogFrequency {
+        #[serde(default = "two")]
+        length_bonus: f64,
+    },
+    /// `ln(count / total) + length_bonus * len`. without a bonus every word costs, so solutions
+    /// made of fewer, likelier words win
+    LogProbability {
+        #[serde(default)]
+        length_bonus: f64,
+    },
+    /// `-exponent * ln(rank) + length_bonus * len`, for lists that are ranked rather than counted
+    ZipfRank {
+        #[serde(default = "one")]
+        exponent: f64,
+        #[serde(default)]
+        length_bonus: f64,
+    },
+}
+
+pub(crate) fn one() -> f64 {
+    1.0
+}
+
+fn two() -> f64 {
+    2.0
+}
+
+impl Default for Scoring {
+    /// the scores the dictionaries always had, without rounding
+    fn default() -> Self {
+        Self::LogFrequency { length_bonus: 2.0 }
+    }
+}
+
+impl WordScorer for Scoring {
+    fn score(&self, word: &str, stats: WordStats) -> f64 {
+        let len = word.len() as f64;
+        match *self {
+            Self::LogFrequency { length_bonus } => {
+                (stats.count.max(1) as f64).log2() + length_bonus * len
+            }
+            Self::LogProbability { length_bonus } => {
+                (stats.count.max(1) as f64 / stats.total.max(1) as f64).ln() + length_bonus * len
+            }
+            Self::ZipfRank {
+                exponent,
+                length_bonus,
+            } => -exponent * (stats.rank as f64).ln() + length_bonus * len,
+        }
+    }
+}
+
+/// scores every word of a list of words and their counts. words with the same count share the
+/// better rank
+pub fn score_words<'a>(
+    scorer: &impl WordScorer,
+    words: impl IntoIterator<Item = (&'a str, u64)>,
+) -> Vec<(&'a str, f64)> {
+    let words = words.into_iter().collect::<Vec<_>>();
+    let total = words.iter().map(|&(_, count)| count).sum();
+
+    let mut counts = words.iter().map(|&(_, count)| count).collect::<Vec<_>>();
+    counts.sort_unstable_by(|a, b| b.cmp(a));
+    let rank = |count| counts.partition_point(|&c| c > count) + 1;
+
+    words
+        .iter()
+        .map(|&(word, count)| {
+            let stats = WordStats {
+                count,
+                rank: rank(count),
+                total,
+            };
+            (word, scorer.score(word, stats))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_words() {
+        let words = [("the", 8), ("cat", 4), ("hat", 4), ("a", 1)];
+
+        let scores = score_words(&Scoring::default(), words);
+        assert_eq!(
+            scores,
+            [("the", 9.0), ("cat", 8.0), ("hat", 8.0), ("a", 2.0)]
+        );
+
+        let zipf: Scoring = serde_json::from_str(r#"{ "kind": "zipf-rank" }"#).unwrap();
+        let ranks = score_words(&zipf, words)
+            .into_iter()
+            .map(|(_, score)| (-score).exp().round() as usize)
+            .collect::<Vec<_>>();
+        assert_eq!(ranks, [1, 2, 2, 4]);
+
+        let prob = Scoring::LogProbability { length_bonus: 0.0 };
+        let total = score_words(&prob, words)
+            .iter()
+            .map(|&(_, score)| score.exp())
+            .sum::<f64>();
+        assert!((total - 1.0).abs() < 1e-9);
+
+        let custom = |word: &str, _: WordStats| -(word.len() as f64);
+        assert_eq!(score_words(&custom, [("ab", 1)]), [("ab", -2.0)]);
+        assert!(serde_json::from_str::<Scoring>(r#"{ "kind": "zipf" }"#).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{joiners, ExpectedNext};
+
+/// the score a dictionary entry contributes to a solution
+pub trait WordValue {
+    fn score(&self) -> f64;
+}
+
+impl WordValue for () {
+    fn score(&self) -> f64 {
+        0.0
+    }
+}
+
+impl WordValue for u64 {
+    fn score(&self) -> f64 {
+        *self as f64
+    }
+}
+
+impl WordValue for f64 {
+    fn score(&self) -> f64 {
+        *self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    Word,
+    Separator,
+    /// a hyphen between the parts of a compound
+    Joiner,
+}
+
+/// a word or separator spanning the bytes `start..end` of a plain text. the values are `f64`s, so
+/// tokens, plain texts and solutions are `PartialEq` but not `Eq` or `Hash`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Token {
+    pub start: usize,
+    pub end: usize,
+    pub kind: TokenKind,
+    /// the dictionary value of a word or the value of a joiner, `None` for separators
+    pub value: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlainText {
+    pub text: String,
+    pub tokens: Vec<Token>,
+}
+
+impl PlainText {
+    pub fn token_text(&self, token: &Token) -> &str {
+        &self.text[token.start..token.end]
+    }
+
+    pub fn words(&self) -> impl Iterator<Item = &str> {
+        self.tokens
+            .iter()
+            .filter(|t| t.kind == TokenKind::Word)
+            .map(|t| self.token_text(t))
+    }
+
+    pub fn score(&self) -> f64 {
+        self.tokens.iter().filter_map(|t| t.value).sum()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Solution {
+    pub left: PlainText,
+    pub right: PlainText,
+    /// the sum of the values of all words on both sides
+    pub score: f64,
+}
+
+impl Solution {
+    pub fn new(left: PlainText, right: PlainText) -> Self {
+        let score = left.score() + right.score();
+        Self { left, right, score }
+    }
+
+    pub fn mirrored(&self) -> Self {
+        Self {
+            left: self.right.clone(),
+            right: self.left.clone(),
+            score: self.score,
+        }
+    }
+}
+
+/// collects the bytes of a plain text together with the state of the side after every byte
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct TextBuilder {
+    text: Vec<u8>,
+    tokens: Vec<Token>,
+    expected: ExpectedNext,
+    word_start: usize,
+}
+
+impl Default for TextBuilder {
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
+impl TextBuilder {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            text: Vec::with_capacity(capacity),
+            tokens: vec![],
+            expected: ExpectedNext::Word,
+            word_start: 0,
+        }
+    }
+
+    /// `value` is the value of the dictionary entry reached by `chr`, which is only recorded if
+    /// the side finishes a word by going on to expect a special char or a joiner, or of the joiner
+    /// `chr`
+    pub fn push(&mut self, chr: u8, next: ExpectedNext, value: Option<f64>) {
+        let at = self.text.len();
+        self.text.push(chr);
+        match (self.expected, next) {
+            (ExpectedNext::AfterWord, _) if joiners().contains(&chr) => {
+                self.tokens.push(Token {
+                    start: at,
+                    end: at + 1,
+                    kind: TokenKind::Joiner,
+                    value: Some(value.unwrap_or(0.0)),
+                });
+                self.word_start = at + 1;
+            }
+            (ExpectedNext::Special | ExpectedNext::AfterWord, _) => {
+                self.tokens.push(Token {
+                    start: at,
+                    end: at + 1,
+                    kind: TokenKind::Separator,
+                    value: None,
+                });
+                self.word_start = at + 1;
+            }
+            (ExpectedNext::Word, ExpectedNext::Special | ExpectedNext::AfterWord) => {
+                self.tokens.push(Token {
+                    start: self.word_start,
+                    end: at + 1,
+                    kind: TokenKind::Word,
+                    value: Some(value.unwrap_or(0.0)),
+                });
+                self.word_start = at + 1;
+            }
+            (ExpectedNext::Word, ExpectedNext::Word) => {}
+        }
+        self.expected = next;
+    }
+
+    pub fn text(&self) -> &[u8] {
+        &self.text
+    }
+
+    pub fn build(self) -> PlainText {
+        PlainText {
+            text: String::from_utf8(self.text).expect("the charset is ascii"),
+            tokens: self.tokens,
+        }
+    }
+}
+
+impl PlainText {
+    /// see [`TextBuilder::push`]
+    pub(crate) fn from_events(
+        events: impl IntoIterator<Item = (u8, ExpectedNext, Option<f64>)>,
+    ) -> Self {
+        let mut builder = TextBuilder::default();
+        for (chr, next, value) in events {
+            builder.push(chr, next, value);
+        }
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenizes_events() {
+        use ExpectedNext::*;
+        let text = PlainText::from_events([
+            (b'a', Special, Some(3.0)),
+            (b' ', Word, None),
+            (b'a', Word, Some(3.0)),
+            (b't', AfterWord, Some(5.0)),
+            (b'-', Word, Some(-2.0)),
+            (b'a', Special, Some(3.0)),
+            (b'.', Special, None),
+        ]);
+
+        assert_eq!(text.text, "a at-a.");
+        assert_eq!(text.words().collect::<Vec<_>>(), ["a", "at", "a"]);
+        assert_eq!(text.score(), 9.0);
+        assert_eq!(
+            text.tokens.iter().map(|t| t.kind).collect::<Vec<_>>(),
+            [
+                TokenKind::Word,
+                TokenKind::Separator,
+                TokenKind::Word,
+                TokenKind::Joiner,
+                TokenKind::Word,
+                TokenKind::Separator
+            ]
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    all::{beam, best_first},
+    crack_all_with, crack_viterbi, Automaton, CrackOptions, Dictionary, Solution,
+};
+
+/// settings shared by all strategies
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SolverConfig {
+    pub options: CrackOptions,
+    /// stop once this many solutions were found, `None` searches for all of them. the depth-first
+    /// and best-first searches go on until they find another one or run out of states, to tell
+    /// whether they are exhaustive
+    pub max_solutions: Option<usize>,
+    /// the most states best-first search keeps at once. when there are more, the least important
+    /// ones are dropped, so some solutions may be missed. this only caps the number of states,
+    /// each of which holds the plain texts read so far, so memory still grows with the cipher.
+    /// beam search is bounded by its width instead, the other strategies ignore this
+    pub max_states: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stats {
+    /// the number of search states that were expanded
+    pub expanded: u64,
+    /// the number of states that were dropped to stay within [`SolverConfig::max_states`] or the
+    /// width of a beam
+    pub dropped: u64,
+    /// whether the solutions are all the strategy could find, or the search was cut short. a
+    /// search that stopped at [`SolverConfig::max_solutions`] is exhaustive if there was no other
+    /// solution
+    pub exhaustive: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Outcome {
+    pub solutions: Vec<Solution>,
+    pub stats: Stats,
+}
+
+/// a search strategy. it is generic over the dictionary rather than `solve`, so that it can be
+/// used as a trait object: `dyn Solver` searches an [`Automaton`], `dyn Solver<D>` any other
+/// dictionary `D`
+pub trait Solver<D: Dictionary<Value = f64> = Automaton<f64>> {
+    fn solve(&self, cipher: &[u8], root: &D, config: &SolverConfig) -> Outcome;
+}
+
+/// the search of [`crate::crack_all`], which finds solutions in the order of the charset
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DepthFirst;
+
+impl<D: Dictionary<Value = f64>> Solver<D> for DepthFirst {
+    fn solve(&self, cipher: &[u8], root: &D, config: &SolverConfig) -> Outcome {
+        let limit = config.max_solutions.unwrap_or(usize::MAX);
+        let mut search = crack_all_with(cipher, root, config.options);
+        let solutions = search.by_ref().take(limit).collect::<Vec<_>>();
+        // with `limit` solutions, the search was only cut short if there is another one
+        let exhaustive = solutions.len() < limit || search.next().is_none();
+        Outcome {
+            stats: Stats {
+                expanded: search.expanded(),
+                exhaustive,
+                ..Default::default()
+            },
+            solutions,
+        }
+    }
+}
+
+/// the search of [`crate::crack_non_rec`], which finds solutions in the order of their importance
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BestFirst;
+
+impl<D: Dictionary<Value = f64>> Solver<D> for BestFirst {
+    fn solve(&self, cipher: &[u8], root: &D, config: &SolverConfig) -> Outcome {
+        best_first(cipher, root, config, |_, _| {})
+    }
+}
+
+/// a breadth-first search that only keeps the `width` most important states of every layer
+#[derive(Debug, Clone, Copy)]
+pub struct Beam {
+    pub width: usize,
+}
+
+impl Default for Beam {
+    fn default() -> Self {
+        Self { width: 1000 }
+    }
+}
+
+impl<D: Dictionary<Value = f64>> Solver<D> for Beam {
+    fn solve(&self, cipher: &[u8], root: &D, config: &SolverConfig) -> Outcome {
+        beam(cipher, root, config, self.width)
+    }
+}
+
+/// [`crate::crack_viterbi`], which only finds the solution with the highest score
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Viterbi;
+
+impl<D: Dictionary<Value = f64>> Solver<D> for Viterbi {
+    fn solve(&self, cipher: &[u8], root: &D, config: &SolverConfig) -> Outcome {
+        let solutions = match config.max_solutions {
+            Some(0) => None,
+            _ => crack_viterbi(cipher, root),
+        };
+        Outcome {
+            stats: Stats {
+                exhaustive: solutions.is_none() && config.max_solutions != Some(0),
+                ..Default::default()
+            },
+            solutions: solutions.into_iter().collect(),
+        }
+    }
+}
+
+/// any of the strategies, e.g. to pick one on the command line
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Strategy {
+    DepthFirst,
+    #[default]
+    BestFirst,
+    Beam(Beam),
+    Viterbi,
+}
+
+impl<D: Dictionary<Value = f64>> Solver<D> for Strategy {
+    fn solve(&self, cipher: &[u8], root: &D, config: &SolverConfig) -> Outcome {
+        match self {
+            Strategy::DepthFirst => DepthFirst.solve(cipher, root, config),
+            Strategy::BestFirst => BestFirst.solve(cipher, root, config),
+            Strategy::Beam(beam) => beam.solve(cipher, root, config),
+            Strategy::Viterbi => Viterbi.solve(cipher, root, config),
+        }
+    }
+}
+
+/// one of `depth-first`, `best-first`, `beam`, `beam:<width>` or `viterbi`
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None => match s {
+                "depth-first" => Ok(Strategy::DepthFirst),
+                "best-first" => Ok(Strategy::BestFirst),
+                "beam" => Ok(Strategy::Beam(Beam::default())),
+                "viterbi" => Ok(Strategy::Viterbi),
+                _ => Err(format!("unknown strategy {s:?}")),
+            },
+            Some(("beam", width)) => width
+                .parse()
+                .map(|width| Strategy::Beam(Beam { width }))
+                .map_err(|e| format!("invalid beam width {width:?}: {e}")),
+            Some(_) => Err(format!("unknown strategy {s:?}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture, xor};
+
+    #[test]
+    fn strategies_agree() {
+        let root = fixture::dict();
+        let cipher = fixture::cipher();
+        let config = SolverConfig::default();
+
+        let texts = |outcome: &Outcome| {
+            let mut texts = outcome
+                .solutions
+                .iter()
+                .map(|s| (s.left.text.clone(), s.right.text.clone()))
+                .collect::<Vec<_>>();
+            texts.sort();
+            texts
+        };
+
+        let all = BestFirst.solve(&cipher, &root, &config);
+        assert!(all.stats.exhaustive);
+        assert_eq!(all.solutions.len(), 4);
+        for strategy in ["depth-first", "beam:4"] {
+            let strategy: Strategy = strategy.parse().unwrap();
+            let outcome = strategy.solve(&cipher, &root, &config);
+            assert!(outcome.stats.exhaustive, "{strategy:?}");
+            assert_eq!(texts(&outcome), texts(&all), "{strategy:?}");
+        }
+
+        let solvers: [&dyn Solver; 2] = [&DepthFirst, &Beam { width: 4 }];
+        for solver in solvers {
+            assert_eq!(texts(&solver.solve(&cipher, &root, &config)), texts(&all));
+        }
+
+        // the empty cipher has no words, so there are no whole words either
+        assert!(crate::crack_all(&[], &root).next().is_none());
+        for strategy in ["best-first", "depth-first", "beam:4", "viterbi"] {
+            let strategy: Strategy = strategy.parse().unwrap();
+            let outcome = strategy.solve(&[], &root, &config);
+            assert!(outcome.solutions.is_empty(), "{strategy:?}");
+        }
+        assert_eq!(crate::count::count_solutions(&[], &root), 0);
+
+        for strategy in ["viterbi", "beam:1"] {
+            let strategy: Strategy = strategy.parse().unwrap();
+            let outcome = strategy.solve(&cipher, &root, &config);
+            assert!(!outcome.stats.exhaustive, "{strategy:?}");
+            for s in &outcome.solutions {
+                assert_eq!(xor(s.left.text.bytes(), s.right.text.bytes()), cipher);
+            }
+        }
+
+        let config = SolverConfig {
+            max_solutions: Some(1),
+            ..config
+        };
+        let capped = BestFirst.solve(
+            &cipher,
+            &root,
+            &SolverConfig {
+                max_states: Some(1),
+                ..Default::default()
+            },
+        );
+        assert!(capped.stats.dropped > 0);
+        assert!(!capped.stats.exhaustive);
+        assert!(texts(&capped).iter().all(|pair| texts(&all).contains(pair)));
+
+        for strategy in [Strategy::BestFirst, Strategy::DepthFirst] {
+            let first = strategy.solve(&cipher, &root, &config);
+            assert_eq!(first.solutions.len(), 1);
+            assert!(!first.stats.exhaustive);
+        }
+
+        // exactly as many solutions as asked for, and no more
+        let solvers: [&dyn Solver; 2] = [&DepthFirst, &BestFirst];
+        for solver in solvers {
+            let all_four = solver.solve(
+                &cipher,
+                &root,
+                &SolverConfig {
+                    max_solutions: Some(4),
+                    ..config
+                },
+            );
+            assert_eq!(all_four.solutions.len(), 4);
+            assert!(all_four.stats.exhaustive);
+        }
+
+        let none = BestFirst.solve(
+            &cipher,
+            &root,
+            &SolverConfig {
+                max_solutions: Some(0),
+                ..config
+            },
+        );
+        assert_eq!(none.solutions, []);
+        assert_eq!(none.stats.expanded, 0);
+
+        assert!("sideways".parse::<Strategy>().is_err());
+    }
+
+    #[test]
+    fn strategies_rank_by_the_score() {
+        // "th" is passed on the way to "the", but only counts where it ends a word
+        let root: Automaton<f64> = fixture::WORDS.into_iter().chain([("th", 20.0)]).collect();
+        let cipher = fixture::cipher();
+        let config = SolverConfig::default();
+
+        let best = Viterbi.solve(&cipher, &root, &config).solutions[0].score;
+        // the importance best-first reports is the score
+        let all = best_first(&cipher, &root, &config, |importance, solution| {
+            assert_eq!(importance, solution.score);
+        });
+        let top = all
+            .solutions
+            .iter()
+            .map(|s| s.score)
+            .fold(f64::MIN, f64::max);
+        assert_eq!(top, best);
+
+        let beam = Beam::default().solve(&cipher, &root, &config).solutions;
+        assert_eq!(beam[0].score, best);
+        assert!(beam.windows(2).all(|w| w[0].score >= w[1].score));
+    }
+}
@@ -0,0 +1,174 @@
+use std::{collections::BTreeMap, fs::File, io, path::Path};
+
+use fst::raw::Fst;
+use memmap2::Mmap;
+
+use crate::{byteset::ByteSet, invalid, Dictionary, NodeId};
+
+/// a dictionary stored as a finite state transducer, which shares suffixes as well as prefixes
+/// and so takes a fraction of the memory of an [`crate::Automaton`] for large word lists. the
+/// file is used as is, so [`FstDict::open`] maps it into memory instead of reading it.
+///
+/// the values are stored as `f32`s. a transducer spreads the value of a word over the edges of its
+/// path, so a node is the address of the transducer's node in the lower 31 bits and the part of
+/// the value collected on the way there in the ones above. the top bit is left to wrappers like
+/// [`crate::unknown::UnknownWords`]
+pub struct FstDict<B = Vec<u8>> {
+    fst: Fst<B>,
+}
+
+impl FstDict {
+    pub fn new<K: AsRef<[u8]>>(words: impl IntoIterator<Item = (K, f64)>) -> Self {
+        // later values replace earlier ones, like for the automaton
+        let words = words
+            .into_iter()
+            .map(|(word, value)| (word.as_ref().to_vec(), (value as f32).to_bits() as u64))
+            .collect::<BTreeMap<_, _>>();
+        let fst = Fst::from_iter_map(words).expect("the words are sorted and unique");
+        Self { fst }
+    }
+}
+
+impl FstDict<Mmap> {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the file must not change while it is mapped, which is the case for
+        // dictionaries that are written once by `save` and only read afterwards
+        let map = unsafe { Mmap::map(&file)? };
+        Self::from_bytes(map)
+    }
+}
+
+impl<B: AsRef<[u8]>> FstDict<B> {
+    /// checks the transducer's checksum, so broken files are rejected before they are walked
+    pub fn from_bytes(bytes: B) -> io::Result<Self> {
+        if bytes.as_ref().len() > ADDR_MASK as usize {
+            return Err(invalid("the dictionary is larger than 2 GiB"));
+        }
+        let fst = Fst::new(bytes).map_err(invalid)?;
+        fst.verify().map_err(invalid)?;
+        Ok(Self { fst })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.fst.as_bytes()
+    }
+
+    /// the number of words
+    pub fn len(&self) -> usize {
+        self.fst.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fst.is_empty()
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        std::fs::write(path, self.as_bytes())
+    }
+}
+
+const ADDR_MASK: NodeId = (1 << 31) - 1;
+
+fn pack(addr: usize, output: u64) -> NodeId {
+    (output << 31) | addr as NodeId
+}
+
+fn unpack(node: NodeId) -> (usize, u64) {
+    ((node & ADDR_MASK) as usize, node >> 31)
+}
+
+impl<B: AsRef<[u8]>> Dictionary for FstDict<B> {
+    type Value = f64;
+
+    fn root(&self) -> NodeId {
+        pack(self.fst.root().addr(), 0)
+    }
+
+    fn children(&self, node: NodeId) -> ByteSet {
+        let (addr, _) = unpack(node);
+        self.fst.node(addr).transitions().map(|t| t.inp).collect()
+    }
+
+    fn child(&self, node: NodeId, label: u8) -> Option<NodeId> {
+        let (addr, output) = unpack(node);
+        let node = self.fst.node(addr);
+        let t = node.transition(node.find_input(label)?);
+        Some(pack(t.addr, output + t.out.value()))
+    }
+
+    /// only checks that the address is inside the transducer and that the top bit is unset, an
+    /// address in the middle of a node can't be told apart without walking all of them
+    fn is_node(&self, node: NodeId) -> bool {
+        node >> 63 == 0 && unpack(node).0 < self.as_bytes().len()
+    }
+
+    fn value(&self, node: NodeId) -> Option<f64> {
+        let (addr, output) = unpack(node);
+        let node = self.fst.node(addr);
+        node.is_final()
+            .then(|| f32::from_bits((output + node.final_output().value()) as u32) as f64)
+    }
+
+    fn has_children(&self, node: NodeId) -> bool {
+        !self.fst.node(unpack(node).0).is_empty()
+    }
+
+    /// the crc32c checksum the transducer ends with, which [`FstDict::from_bytes`] verified, and
+    /// its number of words
+    fn fingerprint(&self) -> u64 {
+        let bytes = self.as_bytes();
+        let checksum = u32::from_le_bytes(bytes[bytes.len() - 4..].try_into().unwrap());
+        ((checksum as u64) << 32) | self.fst.len() as u32 as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solver::BestFirst, xor_strings, Automaton, Solver};
+
+    #[test]
+    fn walks_like_the_automaton() {
+        let words = [
+            ("the", 3.5),
+            ("they", 5.0),
+            ("yes", -2.0),
+            ("kha", 1.0),
+            ("few", 1.0),
+            ("fees", 0.25),
+        ];
+        let dict = FstDict::new(words);
+        let automaton: Automaton<f64> = words.into_iter().collect();
+        assert_eq!(dict.len(), words.len());
+
+        for (word, value) in words {
+            assert_eq!(dict.get(word), Some(value));
+        }
+        assert_eq!(dict.get("th"), None);
+        assert_eq!(dict.walk("thx"), None);
+        let fe = dict.walk("fe").unwrap();
+        assert_eq!(dict.children(fe).iter().collect::<Vec<_>>(), b"ew");
+        let the = (dict.walk("the").unwrap(), automaton.walk("the").unwrap());
+        assert_eq!(dict.answer(the.0), automaton.answer(the.1));
+
+        let path = std::env::temp_dir().join(format!("xoracle-{}.fst", std::process::id()));
+        dict.save(&path).unwrap();
+        let mapped = FstDict::open(&path).unwrap();
+        let cipher = xor_strings("yes", "the");
+        let config = Default::default();
+        assert_eq!(
+            BestFirst.solve(&cipher, &mapped, &config),
+            BestFirst.solve(&cipher, &automaton, &config)
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        let other = FstDict::new(words.map(|(w, v)| (w, v + 1.0)));
+        assert_eq!(mapped.fingerprint(), dict.fingerprint());
+        assert_ne!(other.fingerprint(), dict.fingerprint());
+
+        let mut corrupt = dict.as_bytes().to_vec();
+        corrupt[10] ^= 1;
+        assert!(FstDict::from_bytes(corrupt).is_err());
+    }
+}
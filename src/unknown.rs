@@ -0,0 +1,368 @@
+use std::{hash::Hasher, iter::once};
+
+use itertools::Itertools;
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::{byteset::ByteSet, dictionary::Fnv, Dictionary, NodeId, Queries};
+
+// nodes of unknown words have the top bit set, their length in the 4 bits below it and the
+// letters, as positions in the alphabet of the model, in 5 bits each from the lowest bits up
+const UNKNOWN: NodeId = 1 << 63;
+const LEN_SHIFT: u32 = 59;
+const LETTER_BITS: u32 = 5;
+
+/// the longest unknown word a node has room for
+pub const MAX_LEN: usize = 11;
+
+/// whether and how [`UnknownWords`] takes words that aren't in the dictionary
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UnknownConfig {
+    /// the longest run of letters that is taken as one unknown word, at most [`MAX_LEN`]
+    #[serde(deserialize_with = "max_len")]
+    pub max_len: usize,
+    /// added to the score of every unknown word, so that dictionary words are preferred
+    pub penalty: f64,
+}
+
+fn max_len<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    let max_len = usize::deserialize(deserializer)?;
+    if max_len > MAX_LEN {
+        return Err(de::Error::custom(format!(
+            "unknown words can be at most {MAX_LEN} bytes long"
+        )));
+    }
+    Ok(max_len)
+}
+
+impl Default for UnknownConfig {
+    fn default() -> Self {
+        Self {
+            max_len: 8,
+            penalty: -10.0,
+        }
+    }
+}
+
+/// a bigram model of the letters of words. a word scores the log2 probability of each letter
+/// given the one before it, including the start and the end of the word
+#[derive(Debug, Clone)]
+pub struct CharModel {
+    alphabet: Vec<u8>,
+    // the position of a byte in the alphabet plus one, `0` for bytes outside of it
+    index: [u8; 256],
+    // `log_probs[prev * (alphabet.len() + 1) + next]`, position `0` is the start of a word as
+    // `prev` and its end as `next`
+    log_probs: Vec<f64>,
+}
+
+impl CharModel {
+    /// counts the letter pairs of `words` weighted by their counts. every pair counts once more,
+    /// so unseen ones aren't impossible. words with bytes outside of `alphabet` are skipped
+    pub fn new<'a>(alphabet: &[u8], words: impl IntoIterator<Item = (&'a str, u64)>) -> Self {
+        assert!(
+            alphabet.len() < 1 << LETTER_BITS,
+            "the alphabet must have fewer than 32 letters"
+        );
+        let mut index = [0; 256];
+        for (i, &b) in alphabet.iter().enumerate() {
+            index[b as usize] = i as u8 + 1;
+        }
+
+        let n = alphabet.len() + 1;
+        let mut counts = vec![1.0; n * n];
+        for (word, count) in words {
+            let word = word.as_bytes();
+            if word.iter().any(|&b| index[b as usize] == 0) {
+                continue;
+            }
+            let letters = word.iter().map(|&b| index[b as usize] as usize);
+            for (prev, next) in once(0).chain(letters).chain(once(0)).tuple_windows() {
+                counts[prev * n + next] += count as f64;
+            }
+        }
+        for row in counts.chunks_mut(n) {
+            let total = row.iter().sum::<f64>();
+            row.iter_mut().for_each(|c| *c = (*c / total).log2());
+        }
+
+        Self {
+            alphabet: alphabet.to_vec(),
+            index,
+            log_probs: counts,
+        }
+    }
+
+    pub fn alphabet(&self) -> &[u8] {
+        &self.alphabet
+    }
+
+    /// the log2 probability of `word`, `-inf` if it has bytes outside of the alphabet
+    pub fn log_prob(&self, word: &[u8]) -> f64 {
+        if word.iter().any(|&b| self.index[b as usize] == 0) {
+            return f64::NEG_INFINITY;
+        }
+        let n = self.alphabet.len() + 1;
+        let letters = word.iter().map(|&b| self.index[b as usize] as usize);
+        once(0)
+            .chain(letters)
+            .chain(once(0))
+            .tuple_windows()
+            .map(|(prev, next)| self.log_probs[prev * n + next])
+            .sum()
+    }
+}
+
+/// a dictionary that also takes every run of up to `max_len` letters of the model as a word,
+/// scored by the model plus the penalty. this lets the searches get past names and other words
+/// the dictionary doesn't know. a run that is a dictionary word too gets the dictionary's value.
+///
+/// the nodes of the dictionary must leave the top bit unset, and it has to start from fewer than
+/// [`Queries::CAPACITY`] nodes
+pub struct UnknownWords<'a, D> {
+    dict: &'a D,
+    model: CharModel,
+    config: UnknownConfig,
+    letters: ByteSet,
+}
+
+impl<'a, D: Dictionary<Value = f64>> UnknownWords<'a, D> {
+    /// a `max_len` beyond [`MAX_LEN`] is cut to it
+    pub fn new(dict: &'a D, model: CharModel, mut config: UnknownConfig) -> Self {
+        config.max_len = config.max_len.min(MAX_LEN);
+        let letters = model.alphabet().iter().copied().collect();
+        Self {
+            dict,
+            model,
+            config,
+            letters,
+        }
+    }
+
+    /// the score an unknown `word` gets
+    pub fn score(&self, word: &[u8]) -> f64 {
+        self.config.penalty + self.model.log_prob(word)
+    }
+
+    // whether the letters of the unknown word `node` are in the alphabet and the bits above the
+    // last one are unset
+    fn is_unknown_node(&self, node: NodeId) -> bool {
+        let len = Self::len(node);
+        let letters = node & ((1 << LEN_SHIFT) - 1);
+        len <= self.config.max_len
+            && letters >> (len as u32 * LETTER_BITS) == 0
+            && (0..len).all(|i| {
+                let letter = (letters >> (i as u32 * LETTER_BITS)) & ((1 << LETTER_BITS) - 1);
+                (1..=self.model.alphabet.len() as NodeId).contains(&letter)
+            })
+    }
+
+    /// whether `node` is part of an unknown word rather than of the dictionary
+    pub fn is_unknown(node: NodeId) -> bool {
+        node & UNKNOWN != 0
+    }
+
+    fn len(node: NodeId) -> usize {
+        ((node >> LEN_SHIFT) & 0xf) as usize
+    }
+
+    fn word(&self, node: NodeId) -> Vec<u8> {
+        (0..Self::len(node))
+            .map(|i| {
+                let letter = (node >> (i as u32 * LETTER_BITS)) & ((1 << LETTER_BITS) - 1);
+                self.model.alphabet[letter as usize - 1]
+            })
+            .collect()
+    }
+}
+
+impl<D: Dictionary<Value = f64>> Dictionary for UnknownWords<'_, D> {
+    type Value = f64;
+
+    fn root(&self) -> NodeId {
+        self.dict.root()
+    }
+
+    fn start(&self) -> Queries {
+        let mut start = self.dict.start();
+        // a dictionary that starts from as many nodes as fit leaves no room for unknown words
+        start.insert(UNKNOWN).ok();
+        start
+    }
+
+    fn children(&self, node: NodeId) -> ByteSet {
+        if !Self::is_unknown(node) {
+            self.dict.children(node)
+        } else if Self::len(node) < self.config.max_len {
+            self.letters
+        } else {
+            ByteSet::EMPTY
+        }
+    }
+
+    fn child(&self, node: NodeId, label: u8) -> Option<NodeId> {
+        if !Self::is_unknown(node) {
+            let child = self.dict.child(node, label)?;
+            debug_assert!(!Self::is_unknown(child), "the dictionary uses the top bit");
+            return Some(child);
+        }
+        let len = Self::len(node);
+        let letter = self.model.index[label as usize] as NodeId;
+        if len >= self.config.max_len || letter == 0 {
+            return None;
+        }
+        let letters = node & ((1 << LEN_SHIFT) - 1);
+        Some(
+            UNKNOWN
+                | ((len as NodeId + 1) << LEN_SHIFT)
+                | letters
+                | (letter << (len as u32 * LETTER_BITS)),
+        )
+    }
+
+    fn is_node(&self, node: NodeId) -> bool {
+        if Self::is_unknown(node) {
+            self.is_unknown_node(node)
+        } else {
+            self.dict.is_node(node)
+        }
+    }
+
+    fn are_nodes(&self, nodes: impl IntoIterator<Item = NodeId>) -> bool {
+        let (unknown, known): (Vec<_>, Vec<_>) =
+            nodes.into_iter().partition(|&n| Self::is_unknown(n));
+        self.dict.are_nodes(known) && unknown.into_iter().all(|node| self.is_unknown_node(node))
+    }
+
+    fn value(&self, node: NodeId) -> Option<f64> {
+        if !Self::is_unknown(node) {
+            self.dict.value(node)
+        } else if Self::len(node) > 0 {
+            Some(self.score(&self.word(node)))
+        } else {
+            None
+        }
+    }
+
+    fn is_word(&self, node: NodeId) -> bool {
+        if Self::is_unknown(node) {
+            Self::len(node) > 0
+        } else {
+            self.dict.is_word(node)
+        }
+    }
+
+    fn compound_value(&self) -> Option<f64> {
+        self.dict.compound_value()
+    }
+
+    /// mixes the model and the config into the dictionary's fingerprint, as they change the
+    /// words and their scores as much as the dictionary does
+    fn fingerprint(&self) -> u64 {
+        let mut hash = Fnv::default();
+        hash.write(b"unknown");
+        hash.write_u64(self.dict.fingerprint());
+        hash.write_usize(self.config.max_len);
+        hash.write_u64(self.config.penalty.to_bits());
+        hash.write(&self.model.alphabet);
+        for p in &self.model.log_probs {
+            hash.write_u64(p.to_bits());
+        }
+        hash.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{all::BestFirstSearch, crack_viterbi, xor_strings, Automaton, SolverConfig};
+
+    #[test]
+    fn gets_past_unknown_words() {
+        let words = [("the", 8), ("cat", 4), ("sat", 4), ("yes", 2), ("zap", 1)];
+        let model = CharModel::new(b"abcdefghijklmnopqrstuvwxyz", words);
+        assert!(model.log_prob(b"cat") > model.log_prob(b"qxz"));
+        assert_eq!(model.log_prob(b"c't"), f64::NEG_INFINITY);
+
+        let dict: Automaton<f64> = words
+            .map(|(w, c)| (w, 10.0 + c as f64))
+            .into_iter()
+            .collect();
+        let config = UnknownConfig {
+            max_len: 3,
+            penalty: -20.0,
+        };
+        let unknown = UnknownWords::new(&dict, model, config);
+        let get = |word: &str| {
+            let mut q = unknown.start();
+            word.bytes().for_each(|b| q.advance_all(&unknown, b));
+            q.value(&unknown)
+        };
+        assert_eq!(get("cat"), dict.get("cat"));
+        assert_eq!(get("tom"), Some(unknown.score(b"tom")));
+        assert_eq!(get("thomas"), None);
+        assert_eq!(get(""), None);
+
+        // "tom" isn't a word, so only the unknown words find it. the dictionary words are kept
+        let cipher = xor_strings("cat tom", "the cat");
+        assert!(crack_viterbi(&cipher, &dict).is_none());
+        let best = crack_viterbi(&cipher, &unknown).unwrap();
+        assert_eq!(
+            (best.left.text.as_str(), best.right.text.as_str()),
+            ("cat cat", "the tom")
+        );
+        let expected =
+            2.0 * dict.get("cat").unwrap() + dict.get("the").unwrap() + get("tom").unwrap();
+        assert!((best.score - expected).abs() < 1e-9);
+
+        // the penalty of an unknown word counts once, when it is finished
+        let config = SolverConfig {
+            max_solutions: Some(20),
+            ..Default::default()
+        };
+        let mut search = BestFirstSearch::new(&cipher, &unknown, config);
+        let mut found = 0;
+        while search.advance(|importance, solution| {
+            assert!((importance - solution.score).abs() < 1e-9);
+            found += 1;
+        }) {}
+        assert!(found > 0);
+
+        let tom = b"tom"
+            .iter()
+            .try_fold(UNKNOWN, |node, &b| unknown.child(node, b))
+            .unwrap();
+        assert!(unknown.is_node(tom) && unknown.is_node(UNKNOWN));
+        assert!(!unknown.is_node(tom | (1 << 40)));
+        assert!(!unknown.is_node(UNKNOWN | (4 << LEN_SHIFT)));
+    }
+
+    #[test]
+    fn config_changes_the_fingerprint() {
+        let words = [("the", 8), ("cat", 4)];
+        let model = || CharModel::new(b"abcdefghijklmnopqrstuvwxyz", words);
+        let dict: Automaton<f64> = words.map(|(w, c)| (w, c as f64)).into_iter().collect();
+        let config = UnknownConfig::default();
+        let unknown = UnknownWords::new(&dict, model(), config);
+        assert_ne!(unknown.fingerprint(), dict.fingerprint());
+        let fingerprint = |config| UnknownWords::new(&dict, model(), config).fingerprint();
+        assert_eq!(fingerprint(config), unknown.fingerprint());
+        let shorter = UnknownConfig {
+            max_len: 5,
+            ..config
+        };
+        assert_ne!(fingerprint(shorter), unknown.fingerprint());
+
+        let longer = UnknownConfig {
+            max_len: 20,
+            ..config
+        };
+        assert_eq!(
+            UnknownWords::new(&dict, model(), longer).config.max_len,
+            MAX_LEN
+        );
+        assert!(serde_json::from_str::<UnknownConfig>(r#"{"max_len": 20}"#).is_err());
+        let config = serde_json::from_str::<UnknownConfig>(r#"{"max_len": 11}"#).unwrap();
+        assert_eq!(config.max_len, MAX_LEN);
+    }
+}